@@ -1,63 +1,815 @@
-from metadata_processing import AudioProcessing
+from metadata_processing import AudioProcessing, TAGGABLE_FORMATS
 from decrypt import Decrypt
 from get_audio_resource import GetAudioResource
+from lyrics_processing import LyricsProcessing, LyricsSync, INSTRUMENTAL
+from lyrics_history import LyricsHistory
+from resource_cache import ResourceCache
+from loudness_processing import LoudnessProcessing
+from analysis_processing import AudioAnalysis
+from playlist_processing import PlaylistProcessing
+from itunes_library import ItunesLibrary
+from lyric_card import LyricCard
+from utils.lrclib_api import LRCLIB
+from utils.musicbrainz_api import MusicBrainz
+from utils.acoustid_api import AcoustID
+from utils.storage import file_hash, save_json
 
 import os
+import re
+import json
 import argparse
+import requests
+import shutil
+import signal
 import time
+from concurrent.futures import ThreadPoolExecutor, as_completed
+from urllib.parse import unquote, urlparse
 
 parser = argparse.ArgumentParser()
-parser.add_argument("audiopath", help="path of the audio file or directory", type=str)
+parser.add_argument("audiopath", help="path of the audio file, directory or playlist (m3u/m3u8/xspf/pls), or an http(s) url of an audio file to download", type=str, nargs="?")
+parser.add_argument("--download-dir", help="directory where audio files given as url are saved (default: current directory)", type=str, default=".")
+parser.add_argument("-R", "--recursive", help="also process audio files in subdirectories", action="store_true")
+parser.add_argument("--ext", help="comma separated audio extensions to process in a directory (default: mp3,flac)", type=str, default="mp3,flac")
+parser.add_argument("-j", "--jobs", help="number of files read in parallel by the modes that only read or analyze, e.g. --info, --scan-gain, --loudness (default: 1)", type=int, default=1)
 parser.add_argument("-d", "--delete", help="delete metadata from the audio file", action="store_true")
+parser.add_argument("-t", "--translate", help="attach the online translation to the lyrics", action="store_true")
+parser.add_argument("--translation", help="attach the translation from a separate lrc file to the embedded lyrics (lines are matched by timestamp)", metavar="FILE")
+parser.add_argument("--detach-translation", help="remove the translation lines from the embedded lyrics", action="store_true")
+parser.add_argument("-r", "--romanize", help="add pinyin/romaji lines to chinese and japanese lyrics", action="store_true")
+parser.add_argument("-s", "--sync", help="tap along to a plain lyrics file to create synced lyrics", type=str)
+parser.add_argument("-i", "--info", help="print the metadata of the audio file without modifying it", action="store_true")
+parser.add_argument("--set", help="overwrite a tag field, e.g. --set album=Foo (empty value removes it), can be repeated", action="append", metavar="FIELD=VALUE")
+parser.add_argument("--where", help="only apply --set to files whose tag field equals the value, can be repeated", action="append", metavar="FIELD=VALUE")
+parser.add_argument("--dry-run", help="preview the changes of --set without writing them", action="store_true")
+parser.add_argument("--rating", help="set the star rating (0 removes it)", type=int, choices=range(6))
+parser.add_argument("-c", "--cover", help="embed an image file as the front cover, replacing the existing one", type=str)
+parser.add_argument("--cover-source", help="where to look for missing covers (default: qq)", choices=["qq", "itunes", "caa"], default="qq")
+parser.add_argument("--cover-size", help="max width/height of embedded covers, larger images are downscaled (default: 1000)", type=int, default=1000)
+parser.add_argument("--scan-gain", help="measure loudness with ffmpeg and write ReplayGain tags, album gain is computed per album tag (or per folder when untagged)", action="store_true")
+parser.add_argument("--loudness", help="print the integrated loudness, loudness range and true peak measured with ffmpeg", action="store_true")
+parser.add_argument("--detect-bpm", help="detect the tempo with ffmpeg and write it to the bpm tag", action="store_true")
+parser.add_argument("--detect-key", help="detect the musical key with ffmpeg and write it to the key tag", action="store_true")
+parser.add_argument("--karaoke", help="write an instrumental copy with the centered vocals removed next to the file, keeping tags and lyrics", action="store_true")
+parser.add_argument("--musicbrainz", help="look up the file on MusicBrainz and fill in canonical tags and MBIDs", action="store_true")
+parser.add_argument("--acoustid-key", help="AcoustID api key, identifies untagged files by fingerprint (needs fpcalc)", type=str, default=os.environ.get("ACOUSTID_KEY"))
+parser.add_argument("--itunes-import", help="read an iTunes Library.xml given as the path, write its star ratings into the files and its playlists as m3u8 into DEST", type=str, metavar="DEST")
+parser.add_argument("--itunes-export", help="write the audio files (and the input playlist) to an iTunes compatible Library.xml", type=str, metavar="FILE")
+parser.add_argument("--relink", help="look for missing files of the input playlist by name in this directory and its subdirectories, can be repeated", action="append", metavar="DIR")
+parser.add_argument("--replace-prefix", help="rewrite missing playlist paths starting with OLD to start with NEW, can be repeated", action="append", metavar="OLD=NEW")
+parser.add_argument("--search", help="find files whose title, artist, album or lyrics contain all the words", type=str, metavar="QUERY")
+parser.add_argument("--playlist", help="write the audio files to a playlist, the format follows the extension (m3u/m3u8/xspf/pls)", type=str, metavar="FILE")
+parser.add_argument("--audit", help="report missing, broken or unreadable files and lyrics files without audio, with suggested fixes", action="store_true")
+parser.add_argument("--check-decode", help="decode the whole file with ffmpeg and report decode errors and truncated audio", action="store_true")
+parser.add_argument("--checksum", help="save the hash, size and modification time of the audio files to a manifest", type=str, metavar="MANIFEST")
+parser.add_argument("--verify", help="compare the audio files with a manifest written by --checksum and report corrupted or changed files", type=str, metavar="MANIFEST")
+parser.add_argument("--duplicates", help="list identical files and copies of the same song (title, artist, duration within 2s)", action="store_true")
+parser.add_argument("--organize", help="move the audio files into this directory using --format", type=str, metavar="DEST")
+parser.add_argument("--format", help="path format for --organize (default: \"%%artist%%/%%album%%/%%track%% - %%title%%\")", type=str, default="%artist%/%album%/%track% - %title%")
+parser.add_argument("--fix-encoding", help="repair GBK/Big5/Shift-JIS tags that were stored as latin-1", action="store_true")
+parser.add_argument("--fix-encoding-flac", help="let --fix-encoding also check flac comments, which should already be utf-8", action="store_true")
+parser.add_argument("-v", "--validate", help="check the synced lyrics for timing problems without modifying the file", action="store_true")
+parser.add_argument("--undo", help="restore the lyrics from before the last change", action="store_true")
+parser.add_argument("--redo", help="reapply the last undone lyrics change", action="store_true")
+parser.add_argument("--no-cache", help="always query the online api instead of the local cache", action="store_true")
+parser.add_argument("--cache-limit", help="max number of entries kept in the local cache, the least recently used are removed first (default: 5000)", type=int, default=5000)
+parser.add_argument("--restore-history", help="restore lyrics_history.json from the latest backup, or from the given backup file", nargs="?", const="", metavar="BACKUP")
+parser.add_argument("--cache-stats", help="print the size of the local cache and cover store", action="store_true")
+parser.add_argument("--keep-credits", help="keep credit lines and watermarks in downloaded lyrics", action="store_true")
+parser.add_argument("--clean-pattern", help="extra regex of downloaded lyrics lines to remove, can be repeated", action="append")
+parser.add_argument("--card", help="render lyric lines of a single file with its cover, title and artist into a png image", type=str, metavar="PNG")
+parser.add_argument("--card-lines", help="lyric lines to put on the card, e.g. 5-8 (default: the first 4)", type=str, default="1-4", metavar="START-END")
+parser.add_argument("--font", help="font file used by --card, needs to support chinese/japanese for such lyrics", type=str)
+parser.add_argument("--export-lrc", help="save the embedded lyrics as .lrc files next to the audio, or into DIR", nargs="?", const="", metavar="DIR")
+parser.add_argument("--publish", help="share the synced lyrics of a single file to lrclib.net", action="store_true")
+parser.add_argument("--pattern", help="filename pattern used when tags are missing, e.g. \"%%artist%% - %%title%%\", can be repeated", action="append")
+parser.add_argument("-l", "--lyrics", help="path of a lyrics file (lrc/ttml/krc) to embed instead of searching online", type=str)
 args = parser.parse_args()
+if args.audiopath is None and not args.cache_stats and args.restore_history is None:
+    parser.error("the following arguments are required: audiopath")
+for field in (args.set or []) + (args.where or []):
+    if "=" not in field or not field.split("=", 1)[0]:
+        parser.error(f"expected FIELD=VALUE, got '{field}'")
 
-search = os.path.splitext(os.path.basename(args.audiopath))[0].replace('-', '')
 with open("cookie.txt", "r") as f:
     cookie = f.read()
+history = LyricsHistory()
+cache = None if args.no_cache else ResourceCache(limit=args.cache_limit)
 
 def get_all_audio(dir):
+    extensions = ["." + ext.strip().strip(".").lower() for ext in args.ext.split(",")]
     lst = []
-    for i in os.listdir(dir):
-        lst.append(i) if os.path.splitext(i)[1] == ".mp3" or os.path.splitext(i)[1] == ".flac" else None
+    seen = set()
+    for root, dirs, files in os.walk(dir):
+        dirs.sort()
+        for i in sorted(files):
+            audio = os.path.join(root, i)
+            # 同一个文件通过软链接出现多次时只处理一次
+            if os.path.splitext(i)[1].lower() in extensions and os.path.realpath(audio) not in seen:
+                seen.add(os.path.realpath(audio))
+                lst.append(audio)
+        if not args.recursive:
+            break
     return lst
 
-def auto_meta_match(search_info, audio):
-    music = GetAudioResource(cookie, search_info)
+def get_audio_list(path):
+    if os.path.isdir(path):
+        return get_all_audio(path)
+    return PlaylistProcessing(path).read() if PlaylistProcessing.is_playlist(path) else [path]
+
+def write_playlist(audio_list, path):
+    playlist = PlaylistProcessing(path)
+    tracks = []
+    for audio in audio_list:
+        if not os.path.isfile(audio):
+            # 找不到的文件仍然保留在播放列表中
+            tracks.append((audio, None, None, None))
+            continue
+        try:
+            meta = AudioProcessing(audio=audio)
+            info = meta.metadata_get()
+            tracks.append((audio, info["title"], info["artist"], meta.duration_get()))
+        except Exception as e:
+            # 无法读取的文件同样保留
+            print(f"Error: {e}")
+            tracks.append((audio, None, None, None))
+    playlist.write(tracks)
+    print(f"    {len(tracks)} file(s) written to {path}")
+
+def itunes_import(library):
+    tracks, playlists = ItunesLibrary(library).read()
+    rated, missing = 0, 0
+    for track in tracks:
+        if not os.path.isfile(track["audio"]):
+            missing += 1
+            continue
+        if track["rating"] is None:
+            continue
+        try:
+            if not args.dry_run:
+                AudioProcessing(audio=track["audio"]).rating_set(track["rating"])
+            rated += 1
+        except Exception as e:
+            print(f"Error: {e}")
+    print(f"    {rated} rating(s) imported, {missing} file(s) not found")
+    for name, audio_list in playlists.items():
+        path = os.path.join(args.itunes_import, re.sub(r'[<>:"/\\|?*]', '_', name) + ".m3u8")
+        if args.dry_run:
+            print(f"    {path}: {len(audio_list)} file(s)")
+            continue
+        os.makedirs(args.itunes_import, exist_ok=True)
+        write_playlist(audio_list, path)
+
+def itunes_export(path):
+    tracks = []
+    for audio in progress(get_audio_list(path)):
+        try:
+            meta = AudioProcessing(audio=audio)
+            track = meta.metadata_get()
+            track.update(audio=audio, duration=meta.duration_get(), size=os.path.getsize(audio))
+            tracks.append(track)
+        except Exception as e:
+            print(f"Error: {e}")
+    playlists = {os.path.splitext(os.path.basename(path))[0]: [track["audio"] for track in tracks]} if PlaylistProcessing.is_playlist(path) else {}
+    ItunesLibrary(args.itunes_export).write(tracks, playlists)
+    print(f"    {len(tracks)} file(s) written to {args.itunes_export}")
+
+def safe_duration(audio):
+    try:
+        return AudioProcessing(audio=audio).duration_get()
+    except Exception:
+        return None
+
+def relink_playlist(path):
+    playlist = PlaylistProcessing(path)
+    prefixes = [tuple(os.path.normpath(part) for part in item.split("=", 1)) for item in args.replace_prefix or [] if "=" in item]
+    names = {}
+    for directory in args.relink or []:
+        for root, dirs, files in os.walk(directory):
+            dirs.sort()
+            for i in sorted(files):
+                names.setdefault(i.lower(), []).append(os.path.join(root, i))
+    changes, missing, ambiguous = {}, 0, 0
+    for location, audio, duration in playlist.entries():
+        # 网络流等不是本地文件的条目保持不变
+        if audio is None or os.path.isfile(audio):
+            continue
+        # 替换路径前缀得到的位置是确定的, 按文件名找到的候选还要用播放列表中记录的时长筛选
+        found = next((new + audio[len(old):] for old, new in prefixes
+                      if (audio == old or audio.startswith(old + os.sep)) and os.path.isfile(new + audio[len(old):])), None)
+        candidates = [] if found else [candidate for candidate in names.get(os.path.basename(audio).lower(), []) if os.path.isfile(candidate)]
+        if candidates and duration is not None:
+            candidates = [candidate for candidate in candidates if abs((safe_duration(candidate) or 0) - duration) <= 2000]
+        if len(candidates) > 1:
+            ambiguous += 1
+            print(f"    {audio}: ambiguous, {len(candidates)} candidates")
+            for candidate in candidates:
+                print(f"        {candidate}")
+            continue
+        found = found or (candidates[0] if candidates else None)
+        if found is None:
+            missing += 1
+            print(f"    {audio}: not found")
+            continue
+        print(f"    {audio} -> {found}")
+        changes[location] = found
+    print(f"    {len(changes)} relinked, {ambiguous} ambiguous, {missing} still missing")
+    if changes and not args.dry_run:
+        playlist.relocate(changes)
+
+cancelled = False
+
+def cancel_task(signum, frame):
+    global cancelled
+    if cancelled:
+        raise KeyboardInterrupt
+    cancelled = True
+    print("    Cancelling after the current file, press Ctrl+C again to stop immediately")
+
+def progress(audio_list):
+    # 批量处理时显示进度, Ctrl+C 会在当前文件处理完后停止, 避免写到一半的文件损坏
+    signal.signal(signal.SIGINT, cancel_task)
+    try:
+        for index, audio in enumerate(audio_list):
+            if cancelled:
+                print(f"    Cancelled, {index}/{len(audio_list)} file(s) processed")
+                return
+            if len(audio_list) > 1:
+                print(f"    [{index + 1}/{len(audio_list)}]")
+            yield audio
+    finally:
+        signal.signal(signal.SIGINT, signal.default_int_handler)
+
+def parallel(func, audio_list):
+    # 多线程读取文件, 按完成顺序返回 (audio, 结果), 输出统一在主线程中进行
+    if args.jobs <= 1 or len(audio_list) <= 1:
+        for audio in progress(audio_list):
+            yield audio, func(audio)
+        return
+    signal.signal(signal.SIGINT, cancel_task)
+    executor = ThreadPoolExecutor(max_workers=args.jobs)
+    futures = {executor.submit(func, audio): audio for audio in audio_list}
+    try:
+        for index, future in enumerate(as_completed(futures)):
+            if cancelled:
+                print(f"    Cancelled, {index}/{len(audio_list)} file(s) processed")
+                return
+            print(f"    [{index + 1}/{len(audio_list)}]")
+            yield futures[future], future.result()
+    finally:
+        executor.shutdown(wait=True, cancel_futures=True)
+        signal.signal(signal.SIGINT, signal.default_int_handler)
+
+def download_audio(url):
+    response = requests.get(url, stream=True, timeout=30)
+    response.raise_for_status()
+    # 文件名优先取 Content-Disposition, 其次取 url 的路径, 已存在时加上序号
+    disposition = re.search(r'filename\*?=(?:UTF-8\'\')?"?([^";]+)"?', response.headers.get("Content-Disposition", ""))
+    name = os.path.basename(unquote(disposition.group(1) if disposition else urlparse(url).path)) or "download"
+    base, ext = os.path.splitext(name)
+    audio = os.path.join(args.download_dir, name)
+    index = 1
+    while os.path.exists(audio):
+        audio = os.path.join(args.download_dir, f"{base} ({index}){ext}")
+        index += 1
+    os.makedirs(args.download_dir, exist_ok=True)
+    total, received = int(response.headers.get("Content-Length") or 0), 0
+    try:
+        with open(audio + ".part", 'wb') as f:
+            for chunk in response.iter_content(1 << 16):
+                f.write(chunk)
+                received += len(chunk)
+                print(f"\r    Downloading {name}: " + (f"{received * 100 // total}%" if total else f"{received // 1024} KB"), end="", flush=True)
+        print()
+        os.replace(audio + ".part", audio)
+    finally:
+        # 下载中途出错时删除不完整的文件
+        if os.path.exists(audio + ".part"):
+            os.remove(audio + ".part")
+    if AudioProcessing(audio=audio).audio_format is None:
+        os.remove(audio)
+        raise ValueError(f"{url} is not an audio file")
+    return audio
+
+def get_lyrics_file(audio):
+    # 音频同目录下的同名 ttml/krc/lrc 文件优先于在线歌词
+    for ext in [".ttml", ".krc", ".lrc"]:
+        lyrics_file = os.path.splitext(audio)[0] + ext
+        if os.path.isfile(lyrics_file):
+            return lyrics_file
+    return None
+
+def tap_sync(lyrics_file):
+    session = LyricsSync(lyrics_file)
+    input("Start playback and press Enter at the same moment...")
+    start = time.monotonic()
+    while session.next_line is not None:
+        key = input(f"[{len(session.times) + 1}/{len(session.lines)}] {session.next_line}  (Enter: tap, u: undo, q: finish) ")
+        if key.strip() == "u":
+            session.undo()
+        elif key.strip() == "q":
+            # 没有点击的行没有时间, 结束前确认是否丢弃
+            remaining = len(session.lines) - len(session.times)
+            if input(f"{remaining} line(s) not tapped yet will be dropped, finish anyway? [y/N] ").strip().lower() == "y":
+                break
+        else:
+            session.tap(int((time.monotonic() - start) * 1000))
+    return session.finalize().to_lrc()
+
+def set_metadata(audio):
+    fields = dict(field.split("=", 1) for field in args.set)
+    where = dict(field.split("=", 1) for field in args.where or [])
+    meta = AudioProcessing(audio=audio)
+    current = {name: "" if value is None else str(value) for name, value in meta.metadata_get().items()}
+    if any(current.get(name) != value for name, value in where.items()):
+        return "skipped"
+    changes = {name: value for name, value in fields.items() if current.get(name) != value}
+    if not changes:
+        return "unchanged"
+    print(f"    {audio}")
+    for name, value in changes.items():
+        print(f"        {name}: {current.get(name) or '-'} -> {value or '-'}")
+    if not args.dry_run:
+        meta.metadata_set(changes)
+    return "changed"
+
+def set_metadata_batch(audio_list):
+    result = {"changed": 0, "unchanged": 0, "skipped": 0, "failed": 0}
+    for audio in progress(audio_list):
+        try:
+            result[set_metadata(audio)] += 1
+        except Exception as e:
+            print(f"    {audio}: failed, {e}")
+            result["failed"] += 1
+    print(f"    {'Preview' if args.dry_run else 'Done'}: {', '.join(f'{name} {count}' for name, count in result.items())}")
+
+def set_rating(audio):
+    AudioProcessing(audio=audio).rating_set(args.rating)
+    print(f"    {audio}: rating {args.rating}")
+
+def set_cover(audio):
+    AudioProcessing(audio=audio, cover_size=args.cover_size).cover_set(args.cover)
+    print(f"    {audio}: cover embedded")
+
+def album_key(audio):
+    # 有专辑标签时按 (专辑歌手, 专辑) 分组, 否则把同一目录下的文件视为一张专辑
+    metadata = AudioProcessing(audio=audio).metadata_get()
+    if metadata.get("album"):
+        return metadata.get("albumartist") or metadata.get("artist"), metadata["album"]
+    return None, os.path.dirname(os.path.abspath(audio))
+
+def scan_gain(audio_list):
+    loudness = LoudnessProcessing()
+    results = dict(parallel(lambda audio: (loudness.measure(audio), AudioProcessing(audio=audio).duration_get(), album_key(audio)), audio_list))
+    if cancelled:
+        # 专辑增益需要整张专辑的测量结果, 取消时不写入任何标签
+        return
+    albums = {}
+    for audio in audio_list:
+        albums.setdefault(results[audio][2], []).append(audio)
+    for tracks in albums.values():
+        scan_album_gain(loudness, tracks, results)
+
+def scan_album_gain(loudness, audio_list, results):
+    measurements = [results[audio][0] for audio in audio_list]
+    durations = [results[audio][1] for audio in audio_list]
+    album_gain, album_peak = loudness.album_gain(measurements, durations)
+    for audio, measurement in zip(audio_list, measurements):
+        track_gain, track_peak = loudness.track_gain(measurement)
+        print(f"    {audio}: track {track_gain:.2f} dB, album {album_gain:.2f} dB" if track_gain is not None else f"    {audio}: silent")
+        if track_gain is None or args.dry_run:
+            continue
+        AudioProcessing(audio=audio).replaygain_set({
+            "replaygain_track_gain": f"{track_gain:.2f} dB",
+            "replaygain_track_peak": f"{track_peak:.6f}",
+            "replaygain_album_gain": f"{album_gain:.2f} dB",
+            "replaygain_album_peak": f"{album_peak:.6f}",
+        })
+
+def print_loudness(audio_list):
+    loudness = LoudnessProcessing()
+    for audio, measurement in parallel(loudness.measure, audio_list):
+        integrated = f"{measurement['loudness']:.1f} LUFS" if measurement['loudness'] is not None else "silent"
+        loudness_range = f"{measurement['range']:.1f} LU" if measurement['range'] is not None else "-"
+        peak = f"{measurement['peak']:.1f} dBTP" if measurement['peak'] is not None else "-"
+        print(f"    {audio}: {integrated}, range {loudness_range}, true peak {peak}")
+
+def detect_bpm(audio_list):
+    analysis = AudioAnalysis()
+    for audio, bpm in parallel(analysis.bpm, audio_list):
+        print(f"    {audio}: {bpm} BPM" if bpm else f"    {audio}: no steady beat found")
+        if bpm and not args.dry_run:
+            AudioProcessing(audio=audio).metadata_set({"bpm": bpm})
+
+def detect_key(audio_list):
+    analysis = AudioAnalysis()
+    for audio, key in parallel(analysis.key, audio_list):
+        print(f"    {audio}: {key}" if key else f"    {audio}: silent")
+        if key and not args.dry_run:
+            AudioProcessing(audio=audio).metadata_set({"key": key})
+
+def make_karaoke(audio):
+    name, ext = os.path.splitext(audio)
+    if name.endswith(" (karaoke)"):
+        return
+    output = f"{name} (karaoke){ext}"
+    if args.dry_run:
+        print(f"    {audio} -> {output}")
+        return
+    try:
+        AudioAnalysis().karaoke(audio, output)
+    except RuntimeError as e:
+        print(f"Error: {e}")
+        return
+    # ffmpeg 不一定能复制歌词, 重新写入一次
+    lyrics = AudioProcessing(audio=audio).lyrics_get()
+    if lyrics:
+        AudioProcessing(audio=output).lyrics_set(lyrics)
+    print(f"    {audio} -> {output}")
+
+def musicbrainz_match(audio):
+    meta = AudioProcessing(audio=audio)
+    info = meta.metadata_get()
+    title = info["title"] or os.path.splitext(os.path.basename(audio))[0]
+    candidates = MusicBrainz().search_recording(title, info["artist"], meta.duration_get())[:10]
+    if not candidates:
+        print(f"    {audio}: no match on MusicBrainz")
+        return
+    print(f"    {audio}")
+    for index, candidate in enumerate(candidates):
+        length = LyricsProcessing.format_time(candidate['length'], '()') if candidate['length'] else "(--:--.--)"
+        print(f"        {index + 1}. {candidate['artist']} - {candidate['title']} {length} [{candidate['album']}, {candidate['year'] or '----'}]")
+    choice = input("    Pick a match (Enter: 1, s: skip): ").strip()
+    # 在提示处按下 Ctrl+C 只会标记取消, 这时不写入任何候选
+    if cancelled:
+        return
+    if choice == "s" or (choice and not choice.isdigit()) or not 1 <= int(choice or 1) <= len(candidates):
+        return
+    candidate = candidates[int(choice or 1) - 1]
+    fields = {name: candidate[name] for name in ["title", "artist", "album", "albumartist", "tracknumber", "discnumber", "year"] if candidate[name]}
+    if not args.dry_run:
+        meta.metadata_set(fields)
+        meta.musicbrainz_set(candidate)
+    print(f"    {audio}: {', '.join(f'{name}={value}' for name, value in fields.items())}")
+
+def search_audio(audio):
+    # 返回匹配到的歌词行, 只匹配到标签时返回空列表, 不匹配或格式不支持时返回 None
+    meta = AudioProcessing(audio=audio)
+    if meta.audio_format not in ["audio/mp3", "audio/flac", "audio/mp4"]:
+        return None
+    info = meta.metadata_get()
+    lines = [text for line in LyricsProcessing(meta.lyrics_get() or "").lines
+             for text in [line['text'], line['translation']] if text]
+    content = "\n".join([info["title"] or "", info["artist"] or "", info["album"] or ""] + lines).lower()
+    words = args.search.lower().split()
+    if not all(word in content for word in words):
+        return None
+    return [text for text in lines if any(word in text.lower() for word in words)]
+
+def audit_audio(audio):
+    if not os.path.isfile(audio):
+        return "missing"
+    meta = AudioProcessing(audio=audio)
+    if meta.audio_format is None:
+        return "unreadable"
+    if meta.audio_format not in ["audio/mp3", "audio/flac"]:
+        return "unsupported"
+    try:
+        meta.metadata_get()
+        return "unreadable" if not meta.duration_get() else None
+    except Exception:
+        return "unreadable"
+
+def audit(path):
+    fixes = {
+        "missing": "fix the playlist with --relink/--replace-prefix, or remove the broken link",
+        "unreadable": "the file is damaged or not really audio, replace it",
+        "unsupported": "only mp3 and flac can be tagged, convert it or leave it as is",
+        "orphaned lyrics": "no audio file with the same name, rename or delete it",
+    }
+    problems = {name: [] for name in fixes}
+    for audio, problem in parallel(audit_audio, get_audio_list(path)):
+        if problem:
+            problems[problem].append(audio)
+    if os.path.isdir(path):
+        for root, dirs, files in os.walk(path):
+            dirs.sort()
+            names = {os.path.splitext(i)[0] for i in files if os.path.splitext(i)[1].lower() not in [".lrc", ".ttml", ".krc"]}
+            problems["orphaned lyrics"] += [os.path.join(root, i) for i in sorted(files)
+                                            if os.path.splitext(i)[1].lower() in [".lrc", ".ttml", ".krc"] and os.path.splitext(i)[0] not in names]
+            if not args.recursive:
+                break
+    for name, files in problems.items():
+        if not files:
+            continue
+        print(f"    {name}: {len(files)} file(s), {fixes[name]}")
+        for audio in files:
+            print(f"        {audio}")
+    if not any(problems.values()):
+        print("    No problems found")
+
+def check_decode(audio_list):
+    analysis = AudioAnalysis()
+    def check(audio):
+        decoded, errors = analysis.decode_check(audio)
+        return decoded, errors, AudioProcessing(audio=audio).duration_get()
+    damaged = 0
+    for audio, (decoded, errors, duration) in parallel(check, audio_list):
+        # 解码出的时长比文件头记录的短 1 秒以上视为文件被截断
+        truncated = duration is not None and decoded < duration - 1000
+        if not errors and not truncated:
+            continue
+        damaged += 1
+        print(f"    {audio}: {len(errors)} decode error(s)" + (f", truncated at {LyricsProcessing.format_time(decoded)} of {LyricsProcessing.format_time(duration)}" if truncated else ""))
+        for error in errors[:5]:
+            print(f"        {error}")
+    print(f"    {damaged} damaged file(s)")
+
+def checksum_audio(audio):
+    stat = os.stat(audio)
+    return {"hash": file_hash(audio), "size": stat.st_size, "mtime": int(stat.st_mtime)}
+
+def write_checksums(audio_list, manifest):
+    # 路径相对于清单所在目录保存, 整个音乐文件夹移动后仍然可以校验
+    directory = os.path.dirname(os.path.abspath(manifest))
+    checksums = {os.path.relpath(os.path.abspath(audio), directory): entry for audio, entry in parallel(checksum_audio, audio_list)}
+    if cancelled:
+        return
+    save_json(manifest, checksums, indent=2)
+    print(f"    {len(checksums)} file(s) written to {manifest}")
+
+def verify_checksums(audio_list, manifest):
+    directory = os.path.dirname(os.path.abspath(manifest))
+    with open(manifest, 'r', encoding='utf-8') as f:
+        checksums = json.load(f)
+    audio_list = sorted({os.path.abspath(audio) for audio in audio_list} | {os.path.normpath(os.path.join(directory, audio)) for audio in checksums})
+    problems = {"corrupted": [], "changed": [], "missing": [], "new": []}
+    def verify(audio):
+        expected = checksums.get(os.path.relpath(os.path.abspath(audio), directory))
+        if not os.path.isfile(audio):
+            return "missing"
+        if expected is None:
+            return "new"
+        current = checksum_audio(audio)
+        if current["hash"] == expected["hash"]:
+            return None
+        # 修改时间和大小都没变而内容不同, 多半是磁盘损坏而不是重新打过标签
+        return "corrupted" if current["mtime"] == expected["mtime"] and current["size"] == expected["size"] else "changed"
+    for audio, problem in parallel(verify, audio_list):
+        if problem:
+            problems[problem].append(audio)
+    for name, files in problems.items():
+        for audio in sorted(files):
+            print(f"    {name}: {audio}")
+    print(f"    {len(audio_list) - sum(map(len, problems.values()))} ok, " + ", ".join(f"{len(files)} {name}" for name, files in problems.items()))
+
+def duplicate_key(text):
+    # 忽略大小写、空格和标点后比较标题与歌手
+    return re.sub(r'[\W_]+', '', text or "").lower()
+
+def find_duplicates(audio_list):
+    digests, songs = {}, {}
+    def read(audio):
+        meta = AudioProcessing(audio=audio)
+        return file_hash(audio), meta.metadata_get(), meta.duration_get()
+    for audio, (digest, info, duration) in parallel(read, audio_list):
+        digests[audio] = digest
+        if info["title"]:
+            key = (duplicate_key(info["title"]), duplicate_key(info["artist"]))
+            songs.setdefault(key, []).append((duration or 0, audio))
+    hashes = {}
+    for audio, digest in digests.items():
+        hashes.setdefault(digest, []).append(audio)
+    clusters = [("identical", files) for files in hashes.values() if len(files) > 1]
+    for key, files in songs.items():
+        # 同名歌曲按时长排序后, 相邻时长相差不超过 2 秒的视为同一首
+        files.sort()
+        group = [files[0]]
+        for file in files[1:] + [None]:
+            if file and file[0] - group[-1][0] <= 2000:
+                group.append(file)
+                continue
+            paths = [audio for duration, audio in group]
+            # 内容完全相同的已经在 identical 中列出
+            if len({digests[audio] for audio in paths}) > 1:
+                clusters.append(("similar", paths))
+            group = [file] if file else []
+    if not clusters:
+        print("    No duplicates found")
+    for kind, files in clusters:
+        qualities = {audio: AudioProcessing(audio=audio).quality_get() or {"codec": "?", "bitrate": 0, "lossless": False} for audio in files}
+        best = max(files, key=lambda audio: (qualities[audio]["lossless"], qualities[audio]["bitrate"]))
+        print(f"    {kind}:")
+        for audio in files:
+            quality = qualities[audio]
+            print(f"        {'*' if audio == best else ' '} {audio} ({quality['codec']}, {quality['bitrate']} kbps)")
+
+def organize_audio(audio):
+    target = os.path.join(args.organize, AudioProcessing(audio=audio).path_format(args.format))
+    if os.path.abspath(target) == os.path.abspath(audio):
+        return
+    if os.path.exists(target):
+        print(f"    {audio}: skipped, {target} already exists")
+        return
+    print(f"    {audio} -> {target}")
+    if args.dry_run:
+        return
+    os.makedirs(os.path.dirname(target), exist_ok=True)
+    shutil.move(audio, target)
+    # 同名的歌词文件一起移动
+    for ext in [".ttml", ".krc", ".lrc"]:
+        if os.path.isfile(os.path.splitext(audio)[0] + ext):
+            shutil.move(os.path.splitext(audio)[0] + ext, os.path.splitext(target)[0] + ext)
+
+def fix_encoding(audio):
+    meta = AudioProcessing(audio=audio)
+    repairs = meta.mojibake_get(include_flac=args.fix_encoding_flac)
+    if not repairs:
+        return
+    print(f"    {audio}")
+    for key, (old, new) in repairs.items():
+        print(f"        {key}: {old} -> {new}")
+    if not args.dry_run:
+        meta.mojibake_repair(repairs)
+
+def read_metadata(audio):
+    meta = AudioProcessing(audio=audio)
+    lines = [f"    {audio}"]
+    metadata = meta.metadata_get()
+    chapters = metadata.pop("chapters")
+    for name, value in metadata.items():
+        lines.append(f"        {name}: {value}")
+    for chapter in chapters:
+        lines.append(f"        chapter: {LyricsProcessing.format_time(chapter['start'])} {chapter['title']}")
+    lines.append(f"        gain: track {meta.gain_get()} dB, album {meta.gain_get(album=True)} dB")
+    quality = meta.quality_get()
+    if quality:
+        # 无损格式显示位深/采样率, 例如 FLAC 24/96
+        badge = f"{quality['codec']} {quality['bit_depth']}/{quality['sample_rate'] / 1000:g}" if quality['lossless'] and quality['bit_depth'] else quality['codec']
+        lines.append(f"        audio: {badge}, {quality['sample_rate']} Hz, {quality['channels']} ch, {quality['bitrate']} kbps")
+    return "\n".join(lines)
+
+def validate_lyrics(audio):
+    meta = AudioProcessing(audio=audio)
+    lyrics = args.lyrics or meta.lyrics_get()
+    if not lyrics:
+        print(f"    {audio}: no lyrics")
+        return
+    problems = LyricsProcessing(lyrics).validate(meta.duration_get())
+    print(f"    {audio}: {len(problems)} problem(s)")
+    for problem in problems:
+        stamp = LyricsProcessing.format_time(problem['time']) if problem['time'] is not None else "[--:--.--]"
+        print(f"        {stamp} {problem['type']}: {problem['message']} {problem['text']}")
+
+def apply_metadata(meta):
+    before = meta.lyrics_get()
+    meta.metadata_delete() if args.delete else meta.metadata_processing()
+    after = AudioProcessing(audio=meta.audio).lyrics_get()
+    if before != after:
+        history.record(meta.audio, before, after)
+
+def restore_lyrics(audio):
+    try:
+        lyrics = history.undo(audio) if args.undo else history.redo(audio)
+    except IndexError:
+        print(f"    {audio}: nothing to {'undo' if args.undo else 'redo'}")
+        return
+    AudioProcessing(audio=audio).lyrics_set(lyrics)
+    print(f"    {audio}: lyrics {'restored' if args.undo else 'reapplied'}")
+
+def edit_translation(audio):
+    meta = AudioProcessing(audio=audio)
+    before = meta.lyrics_get()
+    if not before:
+        print(f"    {audio}: no lyrics")
+        return
+    lyrics = LyricsProcessing(before)
+    after = (lyrics.attach_translation(LyricsProcessing(args.translation)) if args.translation else lyrics.detach_translation()).to_lrc()
+    if after == before:
+        print(f"    {audio}: unchanged")
+        return
+    if not args.dry_run:
+        meta.lyrics_set(after)
+        history.record(audio, before, after)
+    print(f"    {audio}: translation {'attached' if args.translation else 'removed'}")
+
+def publish_lyrics(audio):
+    meta = AudioProcessing(audio=audio)
+    info = meta.metadata_get()
+    lyrics = LyricsProcessing(args.lyrics or meta.lyrics_get() or "")
+    duration = meta.duration_get()
+    if not info["title"] or not info["artist"] or not duration or not any(line['time'] is not None for line in lyrics.lines):
+        print(f"    {audio}: title, artist, duration and synced lyrics are required to publish")
+        return
+    if input(f"Publish lyrics of {info['artist']} - {info['title']} to lrclib.net? [y/N] ").strip().lower() != "y":
+        return
+    LRCLIB().publish(info["title"], info["artist"], info["album"] or "", round(duration / 1000),
+                     lyrics.to_plain(), lyrics.to_lrc(words=False, translation=False, romanization=False, tags=False))
+    print(f"    {audio}: published")
+
+def lyric_card(audio):
+    meta = AudioProcessing(audio=audio)
+    info = meta.metadata_get()
+    lines = [line['text'] for line in LyricsProcessing(args.lyrics or meta.lyrics_get() or "").lines if line['text'].strip()]
+    start, _, end = args.card_lines.partition("-")
+    selected = lines[int(start) - 1:int(end or start)]
+    if not selected:
+        print(f"    {audio}: no lyrics in lines {args.card_lines}, the file has {len(lines)} line(s)")
+        return
+    LyricCard(selected, info["title"], info["artist"], meta.cover_get(), args.font).render(args.card)
+    print(f"    {audio}: lyric card written to {args.card}")
+
+def export_lrc(audio):
+    lyrics = AudioProcessing(audio=audio).lyrics_get()
+    if not lyrics:
+        return "no lyrics"
+    name = os.path.splitext(os.path.basename(audio))[0] + ".lrc"
+    target = os.path.join(args.export_lrc or os.path.dirname(audio), name)
+    if os.path.exists(target):
+        return "exists"
+    print(f"    {audio} -> {target}")
+    if not args.dry_run:
+        os.makedirs(os.path.dirname(target) or ".", exist_ok=True)
+        with open(target, 'w', encoding='utf-8') as f:
+            # 原样写出内嵌的歌词, 重新生成会改变行顺序和时间精度
+            f.write(lyrics)
+    return "exported"
+
+def filename_search(audio):
+    fields = AudioProcessing.filename_parse(audio, args.pattern)
+    search_info = " ".join(fields[name] for name in ["artist", "title"] if fields.get(name))
+    return search_info or os.path.splitext(os.path.basename(audio))[0].replace('-', ' ')
+
+def identify_search(audio, search_info):
+    # 没有标题和歌手的文件先用声纹识别, 再用识别结果搜索
+    if not args.acoustid_key:
+        return search_info
+    title, artist, lyrics, cover = AudioProcessing(audio=audio).metadata_check()
+    if title or artist:
+        return search_info
+    try:
+        identified = AcoustID(args.acoustid_key).identify(audio)
+    except Exception as e:
+        print(f"Error: {e}")
+        return search_info
+    return f"{identified['artist']} {identified['title']}" if identified else search_info
+
+def auto_meta_match(search_info, audio, local_lyrics=False):
+    music = GetAudioResource(cookie, search_info, cache)
     music_list = music.audio_search()
     meta_check = AudioProcessing(audio=audio)
     title, artist, lyrics, cover = meta_check.metadata_check()
+    if not music_list:
+        # 搜索不到时只能用文件名中解析出的标题和歌手
+        fields = AudioProcessing.filename_parse(audio, args.pattern)
+        song_name = fields.get("title") if not title else None
+        singer = fields.get("artist") if not artist else None
+        return song_name, singer, None, None, bool(INSTRUMENTAL.search(search_info))
     song_id = music_list[0]['songmid']
     song_name = music_list[0]['songname'] if not title else None
-    singer = music_list[0]['singer'][0]['name'] if not artist else None
-    audio_lyrics = music.audio_lyrics_get(song_id) if not lyrics else None
+    singer = [singer['name'] for singer in music_list[0]['singer']] if not artist else None
+    # 文件名或搜索结果标明是纯音乐/伴奏, 或者有本地歌词文件时不再获取在线歌词
+    instrumental = bool(INSTRUMENTAL.search(search_info) or INSTRUMENTAL.search(music_list[0]['songname']))
+    audio_lyrics = music.audio_lyrics_get(song_id) if not lyrics and not instrumental and not local_lyrics else None
+    if audio_lyrics and LyricsProcessing(audio_lyrics).is_instrumental():
+        instrumental, audio_lyrics = True, None
+    if audio_lyrics and not args.keep_credits:
+        audio_lyrics = LyricsProcessing(audio_lyrics).clean(args.clean_pattern).to_lrc()
+    if audio_lyrics and args.translate:
+        translation = music.audio_translation_get(song_id)
+        audio_lyrics = LyricsProcessing(audio_lyrics).attach_translation(translation).to_lrc() if translation else audio_lyrics
     cover_id = music_list[0]['albummid']
-    audio_cover = music.audio_cover_get(cover_id) if not cover else None
-    return song_name, singer, audio_lyrics, audio_cover
+    if cover:
+        audio_cover = None
+    elif args.cover_source == "qq":
+        audio_cover = music.audio_cover_get(cover_id)
+    else:
+        audio_cover = music.audio_artwork_get(music_list[0]['albumname'], music_list[0]['singer'][0]['name'], args.cover_source)
+    return song_name, singer, audio_lyrics, audio_cover, instrumental
 
-if __name__ == "__main__":
-    if os.path.isdir(args.audiopath):
-        audio = get_all_audio(args.audiopath)
-        with open("batch.log", "a") as f:
-            f.truncate(0)
-        for i in audio:
-            audio_name = os.path.abspath('.') + args.audiopath + i
-            batch_search = os.path.splitext(i)[0].replace('-', ' ')
-            title, artist, lyrics, cover = auto_meta_match(batch_search, audio_name)
-            meta = AudioProcessing(
-                audio=audio_name, 
-                title=title, 
-                artist=artist, 
-                lyrics=lyrics, 
-                cover=cover
-            )
-            meta.metadata_delete() if args.delete else meta.metadata_processing()
-            meta_check = AudioProcessing(audio=audio_name)
-            title_status, artist_status, lyrics_status, cover_status = meta_check.metadata_check()
-            info = f'''
+def batch_process(audio_name):
+    batch_search = identify_search(audio_name, filename_search(audio_name))
+    lyrics_file = get_lyrics_file(audio_name)
+    title, artist, lyrics, cover, instrumental = auto_meta_match(batch_search, audio_name, lyrics_file is not None)
+    lyrics_embedded = AudioProcessing(audio=audio_name).metadata_check()[2]
+    lyrics = LyricsProcessing(lyrics_file).to_lrc() if lyrics_file and not lyrics_embedded else lyrics
+    lyrics = LyricsProcessing(lyrics).romanize().to_lrc() if lyrics and args.romanize else lyrics
+    meta = AudioProcessing(
+        audio=audio_name, 
+        title=title, 
+        artist=artist, 
+        lyrics=lyrics, 
+        cover=cover,
+        cover_size=args.cover_size
+    )
+    apply_metadata(meta)
+    meta_check = AudioProcessing(audio=audio_name)
+    title_status, artist_status, lyrics_status, cover_status = meta_check.metadata_check()
+    info = f'''
     time: {time.strftime("%H:%M:%S", time.localtime())}
     Args: {"delete" if args.delete else "add"}
     Audio: "{audio_name}"
     Search: {batch_search}
+    Instrumental: {instrumental}
     Metadata Status Input: 
         artist: {artist_status}, 
         title: {title_status}, 
@@ -74,26 +826,152 @@ if __name__ == "__main__":
         lyrics: {lyrics_status | bool(lyrics) if not args.delete else False}, 
         cover: {cover_status | bool(cover) if not args.delete else False}
             '''
-            print(info)
-            with open("batch.log", "a") as f:
-                f.writelines(info)
+    print(info)
+    with open("batch.log", "a") as f:
+        f.writelines(info)
+
+def restore_history(backup):
+    try:
+        print(f"    {history.path} restored from {history.restore(backup or None)}")
+    except (OSError, ValueError) as e:
+        print(f"Error: {e}")
+
+def print_cache_stats():
+    stats = ResourceCache(limit=args.cache_limit).stats()
+    print(f"    entries: {stats['entries']}/{stats['limit']} ({stats['expired']} expired), {stats['size'] / 1024:.1f} KB")
+    print(f"    covers: {stats['covers']}, {stats['cover_size'] / 1024 / 1024:.1f} MB")
+
+if __name__ == "__main__":
+    if args.audiopath and re.match(r'https?://', args.audiopath):
+        try:
+            args.audiopath = download_audio(args.audiopath)
+        except (requests.exceptions.RequestException, ValueError) as e:
+            print(f"Error: {e}")
+            raise SystemExit(1)
+    if args.cache_stats:
+        print_cache_stats()
+    elif args.restore_history is not None:
+        restore_history(args.restore_history)
+    elif args.info:
+        for audio, metadata in parallel(read_metadata, get_audio_list(args.audiopath)):
+            print(metadata)
+    elif args.set:
+        set_metadata_batch(get_audio_list(args.audiopath))
+    elif args.rating is not None:
+        for audio in progress(get_audio_list(args.audiopath)):
+            set_rating(audio)
+    elif args.cover:
+        for audio in progress(get_audio_list(args.audiopath)):
+            set_cover(audio)
+    elif args.scan_gain:
+        scan_gain(get_audio_list(args.audiopath))
+    elif args.loudness:
+        print_loudness(get_audio_list(args.audiopath))
+    elif args.detect_bpm:
+        detect_bpm(get_audio_list(args.audiopath))
+    elif args.detect_key:
+        detect_key(get_audio_list(args.audiopath))
+    elif args.karaoke:
+        for audio in progress(get_audio_list(args.audiopath)):
+            make_karaoke(audio)
+    elif args.musicbrainz:
+        for audio in progress(get_audio_list(args.audiopath)):
+            musicbrainz_match(audio)
+            time.sleep(1)
+    elif args.playlist:
+        write_playlist(get_audio_list(args.audiopath), args.playlist)
+    elif args.itunes_import:
+        itunes_import(args.audiopath)
+    elif args.itunes_export:
+        itunes_export(args.audiopath)
+    elif args.relink or args.replace_prefix:
+        relink_playlist(args.audiopath)
+    elif args.search:
+        found = 0
+        for audio, lines in parallel(search_audio, get_audio_list(args.audiopath)):
+            if lines is None:
+                continue
+            found += 1
+            print(f"    {audio}")
+            for text in lines[:3]:
+                print(f"        {text}")
+        print(f"    {found} file(s) found")
+    elif args.audit:
+        audit(args.audiopath)
+    elif args.check_decode:
+        check_decode(get_audio_list(args.audiopath))
+    elif args.checksum:
+        write_checksums(get_audio_list(args.audiopath), args.checksum)
+    elif args.verify:
+        verify_checksums(get_audio_list(args.audiopath), args.verify)
+    elif args.duplicates:
+        find_duplicates(get_audio_list(args.audiopath))
+    elif args.organize:
+        for audio in progress(get_audio_list(args.audiopath)):
+            organize_audio(audio)
+    elif args.fix_encoding:
+        for audio in progress(get_audio_list(args.audiopath)):
+            fix_encoding(audio)
+    elif args.validate:
+        for audio in progress(get_audio_list(args.audiopath)):
+            validate_lyrics(audio)
+    elif args.export_lrc is not None:
+        results = [export_lrc(audio) for audio in progress(get_audio_list(args.audiopath))]
+        print(f"    {results.count('exported')} exported, {results.count('exists')} skipped (.lrc exists), {results.count('no lyrics')} without lyrics")
+    elif args.card:
+        lyric_card(args.audiopath)
+    elif args.publish:
+        publish_lyrics(args.audiopath)
+    elif args.translation:
+        edit_translation(args.audiopath)
+    elif args.detach_translation:
+        for audio in progress(get_audio_list(args.audiopath)):
+            edit_translation(audio)
+    elif args.undo or args.redo:
+        for audio in progress(get_audio_list(args.audiopath)):
+            restore_lyrics(audio)
+    elif os.path.isdir(args.audiopath) or PlaylistProcessing.is_playlist(args.audiopath):
+        audio = get_audio_list(args.audiopath)
+        untaggable = [ext for ext in args.ext.split(",") if ext.strip().strip(".").lower() not in ["mp3", "flac"]]
+        if untaggable and os.path.isdir(args.audiopath):
+            print(f"Warning: {', '.join(untaggable)} files can't be tagged in this mode and will be skipped")
+        with open("batch.log", "a") as f:
+            f.truncate(0)
+        for audio_name in progress(audio):
+            if AudioProcessing(audio=audio_name).audio_format not in TAGGABLE_FORMATS:
+                print(f"    {audio_name}: skipped, not a readable mp3 or flac file")
+                continue
+            try:
+                batch_process(audio_name)
+            except Exception as e:
+                print(f"    {audio_name}: failed, {e}")
             time.sleep(0.5)
     else:
         meta_check = AudioProcessing(audio=args.audiopath)
-        title, artist, lyrics, cover = auto_meta_match(search, args.audiopath)
+        if meta_check.audio_format not in TAGGABLE_FORMATS:
+            print(f"Error: {args.audiopath} is not an mp3 or flac file, only these formats can be tagged in this mode")
+            raise SystemExit(1)
+        search = identify_search(args.audiopath, filename_search(args.audiopath))
         title_status, artist_status, lyrics_status, cover_status = meta_check.metadata_check()
+        lyrics_file = args.lyrics or get_lyrics_file(args.audiopath)
+        title, artist, lyrics, cover, instrumental = auto_meta_match(search, args.audiopath, lyrics_file is not None or args.sync is not None)
+        lyrics = LyricsProcessing(lyrics_file).to_lrc() if lyrics_file and not lyrics_status else lyrics
+        lyrics = tap_sync(args.sync) if args.sync and not lyrics_status else lyrics
+        lyrics = LyricsProcessing(lyrics).romanize().to_lrc() if lyrics and args.romanize else lyrics
         meta = AudioProcessing(
             audio=args.audiopath,
             title=title, 
             artist=artist, 
             lyrics=lyrics, 
-            cover=cover
+            cover=cover,
+            cover_size=args.cover_size
             )
-        meta.metadata_delete() if args.delete else meta.metadata_processing()
+        apply_metadata(meta)
         info = f'''
     Args: {"delete" if args.delete else "add"}
     Audio: {args.audiopath}
     Search: {search}
+    Instrumental: {instrumental}
     Metadata Status Input: 
         artist: {artist_status}, 
         title: {title_status}, 
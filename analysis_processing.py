@@ -0,0 +1,119 @@
+from array import array
+from operator import mul
+import math
+import os
+import subprocess
+
+KEY_NAMES = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"]
+# Krumhansl-Kessler 调性轮廓, 从主音开始
+MAJOR_PROFILE = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88]
+MINOR_PROFILE = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17]
+
+
+class AudioAnalysis():
+
+    def __init__(self, ffmpeg="ffmpeg", rate=11025) -> None:
+        self.ffmpeg = ffmpeg
+        self.rate = rate
+
+    def decode(self, audio, limit=180):
+        # 解码为单声道 16 位 pcm, 只取前 limit 秒, 分析节奏和调性已经足够
+        cmd = [self.ffmpeg, '-hide_banner', '-nostats', '-v', 'error',
+               '-i', audio, '-t', str(limit),
+               '-ac', '1', '-ar', str(self.rate),
+               '-f', 's16le', '-']
+        result = subprocess.run(cmd, capture_output=True)
+        if result.returncode != 0:
+            stderr = result.stderr.decode('utf-8', errors='replace').strip()
+            raise RuntimeError(stderr.splitlines()[-1] if stderr else "ffmpeg failed")
+        samples = array('h')
+        samples.frombytes(result.stdout[:len(result.stdout) // 2 * 2])
+        return samples
+
+    def decode_check(self, audio):
+        # 完整解码一遍, 返回 (解码出的时长毫秒, 错误信息列表)
+        cmd = [self.ffmpeg, '-hide_banner', '-nostats', '-v', 'error',
+               '-i', audio, '-map', '0:a:0',
+               '-f', 'null', '-progress', 'pipe:1', '-']
+        result = subprocess.run(cmd, capture_output=True, text=True, encoding='utf-8', errors='replace')
+        times = [int(line.split("=", 1)[1]) for line in result.stdout.splitlines() if line.startswith("out_time_us=") and line.split("=", 1)[1].isdigit()]
+        errors = list(dict.fromkeys(line.strip() for line in result.stderr.splitlines() if line.strip()))
+        return (times[-1] // 1000 if times else 0), errors
+
+    def karaoke(self, audio, output):
+        # 左右声道相减去掉居中的人声, 低频(贝斯、底鼓)通常也在中间, 所以保留原来 120Hz 以下的部分
+        # 标签和封面原样复制, 编码与原文件一致
+        codec = {'.mp3': ['-c:a', 'libmp3lame', '-q:a', '2'], '.flac': ['-c:a', 'flac']}.get(os.path.splitext(output)[1].lower(), [])
+        cmd = [self.ffmpeg, '-hide_banner', '-nostats', '-v', 'error', '-y',
+               '-i', audio,
+               '-filter_complex', '[0:a:0]asplit[a][b];[a]pan=stereo|c0=c0-c1|c1=c1-c0[side];[b]lowpass=f=120[bass];[side][bass]amix=inputs=2:normalize=0[out]',
+               '-map', '[out]', '-map', '0:v?', '-c:v', 'copy', '-map_metadata', '0',
+               *codec, output]
+        result = subprocess.run(cmd, capture_output=True, text=True, encoding='utf-8', errors='replace')
+        if result.returncode != 0:
+            raise RuntimeError(result.stderr.strip().splitlines()[-1] if result.stderr.strip() else "ffmpeg failed")
+        return output
+
+    @staticmethod
+    def __onset_envelope(samples, hop):
+        # 每帧能量取对数后的正向差分, 近似鼓点等起音的强度
+        energies = [math.log1p(sum(x * x for x in samples[i:i + hop]) / hop) for i in range(0, len(samples) - hop + 1, hop)]
+        flux = [max(0.0, b - a) for a, b in zip(energies, energies[1:])]
+        mean = sum(flux) / len(flux) if flux else 0.0
+        return [value - mean for value in flux]
+
+    def bpm(self, audio, low=60, high=200, hop=110):
+        envelope = self.__onset_envelope(self.decode(audio), hop)
+        fps = self.rate / hop
+        lags = range(max(1, int(fps * 60 / high)), int(fps * 60 / low) + 2)
+        if len(envelope) <= lags[-1] * 4:
+            return None
+        correlation = {lag: sum(a * b for a, b in zip(envelope, envelope[lag:])) / (len(envelope) - lag) for lag in lags}
+        # 以 120 BPM 为中心按八度加权, 减少识别成半速或倍速
+        weighted = {lag: value * math.exp(-0.5 * math.log2(fps * 60 / lag / 120) ** 2) for lag, value in correlation.items()}
+        best = max(list(lags)[1:-1], key=lambda lag: weighted[lag])
+        if correlation[best] <= 0:
+            return None
+        # 抛物线插值得到小数的延迟
+        left, center, right = correlation[best - 1], correlation[best], correlation[best + 1]
+        denominator = left - 2 * center + right
+        offset = 0.5 * (left - right) / denominator if denominator else 0.0
+        return round(fps * 60 / (best + offset))
+
+    @staticmethod
+    def __correlate(x, y):
+        mean_x, mean_y = sum(x) / len(x), sum(y) / len(y)
+        covariance = sum((a - mean_x) * (b - mean_y) for a, b in zip(x, y))
+        deviation = math.sqrt(sum((a - mean_x) ** 2 for a in x) * sum((b - mean_y) ** 2 for b in y))
+        return covariance / deviation if deviation else 0.0
+
+    def __chroma(self, samples, size=4096, frames=48, low=48, high=96):
+        # 在均匀分布的若干帧上计算 C3~B6 各个半音的能量, 再按音级累加
+        starts = [int(i * (len(samples) - size) / frames) for i in range(frames)] if len(samples) > size else []
+        window = [0.5 - 0.5 * math.cos(2 * math.pi * i / size) for i in range(size)]
+        tables = []
+        for note in range(low, high):
+            frequency = 440 * 2 ** ((note - 69) / 12)
+            step = 2 * math.pi * frequency / self.rate
+            tables.append((note % 12,
+                           [w * math.cos(step * i) for i, w in enumerate(window)],
+                           [w * math.sin(step * i) for i, w in enumerate(window)]))
+        chroma = [0.0] * 12
+        for start in starts:
+            frame = samples[start:start + size]
+            for pitch_class, cos_table, sin_table in tables:
+                real, imag = sum(map(mul, frame, cos_table)), sum(map(mul, frame, sin_table))
+                chroma[pitch_class] += math.log1p(math.sqrt(real * real + imag * imag) / size)
+        return chroma
+
+    def key(self, audio):
+        # 返回 "C"、"F#m" 这样的调名, 与 TKEY/INITIALKEY 的写法一致
+        chroma = self.__chroma(self.decode(audio))
+        if not any(chroma):
+            return None
+        candidates = []
+        for tonic in range(12):
+            rotated = chroma[tonic:] + chroma[:tonic]
+            candidates.append((self.__correlate(rotated, MAJOR_PROFILE), KEY_NAMES[tonic]))
+            candidates.append((self.__correlate(rotated, MINOR_PROFILE), KEY_NAMES[tonic] + "m"))
+        return max(candidates)[1]
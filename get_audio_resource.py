@@ -1,17 +1,48 @@
 from utils.qq_music_api import QQ_Music
+from utils.artwork_api import Artwork
+from utils.storage import save_bytes
+import hashlib
+import os
 import requests
 
 class GetAudioResource():
 
-    def __init__(self, cookie, search):
+    def __init__(self, cookie, search, cache=None):
         self.cookie = cookie
         self.search = search
+        self.cache = cache
+
+    def __cached(self, key, fetch):
+        # 没有传入缓存或缓存过期时才请求接口
+        value = self.cache.get(key) if self.cache is not None else None
+        if value is None:
+            value = fetch()
+            if self.cache is not None and value:
+                self.cache.set(key, value)
+        return value
+
+    def __cover_cached(self, url, download, cover_dir):
+        # 封面按内容的 sha1 保存在 cover_dir 中, 缓存里只记录 url 对应的 sha1
+        digest = self.cache.get(f"cover|{url}") if self.cache is not None else None
+        cover_file = os.path.join(cover_dir, f"{digest}.jpg") if digest else None
+        if cover_file and os.path.isfile(cover_file):
+            with open(cover_file, 'rb') as f:
+                return f.read()
+        data = download(url)
+        if self.cache is None or not data:
+            return data
+        digest = hashlib.sha1(data).hexdigest()
+        os.makedirs(cover_dir, exist_ok=True)
+        cover_file = os.path.join(cover_dir, f"{digest}.jpg")
+        if not os.path.isfile(cover_file):
+            save_bytes(cover_file, data)
+        self.cache.set(f"cover|{url}", digest)
+        return data
 
     def audio_search(self):
         qq_music = QQ_Music()
         qq_music._cookies = qq_music.set_cookie(self.cookie)
-        list_search = qq_music.search_music(self.search, 10)
-        return list_search
+        return self.__cached(f"search|{self.search.strip().lower()}", lambda: qq_music.search_music(self.search, 10))
     
     def audio_info_get(self, mid):
         qq_music = QQ_Music()
@@ -20,15 +51,34 @@ class GetAudioResource():
     
     def audio_lyrics_get(self, mid):
         qq_music = QQ_Music()
-        lyrics = qq_music.get_lyrics(mid)
-        return lyrics
+        return self.__cached(f"lyrics|{mid}", lambda: qq_music.get_lyrics(mid))
+
+    def audio_translation_get(self, mid):
+        qq_music = QQ_Music()
+        translation = self.__cached(f"translation|{mid}", lambda: qq_music.get_lyrics(mid, translate=True))
+        # 没有翻译时接口会返回原歌词
+        return translation if translation != self.audio_lyrics_get(mid) else None
     
-    def audio_cover_get(self, mid):
-        try:
-            url = f'http://y.qq.com/music/photo_new/T002R800x800M000{mid}.jpg'
+    def audio_cover_get(self, mid, cover_dir="covers"):
+        def download(url):
             response = requests.get(url)
             response.raise_for_status()
             return response.content
+        try:
+            return self.__cover_cached(f'http://y.qq.com/music/photo_new/T002R800x800M000{mid}.jpg', download, cover_dir)
+        except requests.exceptions.RequestException as e:
+            print(f"Error: {e}")
+            return None
+
+    def audio_artwork_get(self, album, artist, source, cover_dir="covers"):
+        # 选择分辨率最高的候选封面, 下载后保存在 cover_dir 中
+        artwork = Artwork()
+        try:
+            candidates = self.__cached(f"artwork|{source}|{album}|{artist}".lower(), lambda: artwork.search(source, album, artist))
+            if not candidates:
+                return None
+            best = max(candidates, key=lambda candidate: candidate['width'] or 0)
+            return self.__cover_cached(best['url'], artwork.download, cover_dir)
         except requests.exceptions.RequestException as e:
             print(f"Error: {e}")
-            return None
\ No newline at end of file
+            return None
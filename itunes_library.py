@@ -0,0 +1,80 @@
+from urllib.parse import urlparse
+from urllib.request import url2pathname
+import os
+import pathlib
+import plistlib
+
+
+class ItunesLibrary():
+
+    def __init__(self, library) -> None:
+        self.library = library
+
+    @staticmethod
+    def __path(location):
+        # iTunes 的 Location 形如 file://localhost/C:/Music/a.mp3
+        if not location or not location.startswith("file:"):
+            return None
+        return os.path.normpath(url2pathname(urlparse(location).path))
+
+    def read(self):
+        # 返回 (歌曲列表, {播放列表名: 路径列表}), 评分为 0~5 星, 由专辑评分推算的不算
+        with open(self.library, 'rb') as f:
+            library = plistlib.load(f)
+        tracks = {}
+        for track in library.get("Tracks", {}).values():
+            audio = self.__path(track.get("Location"))
+            if audio is None:
+                continue
+            rating = track.get("Rating") if not track.get("Rating Computed") else None
+            tracks[track["Track ID"]] = {
+                "audio": audio,
+                "rating": round(rating / 20) if rating is not None else None,
+            }
+        playlists = {}
+        for playlist in library.get("Playlists", []):
+            # 跳过资料库、音乐等内置列表和文件夹
+            if playlist.get("Master") or playlist.get("Distinguished Kind") or playlist.get("Folder"):
+                continue
+            items = [tracks[item["Track ID"]]["audio"] for item in playlist.get("Playlist Items", []) if item["Track ID"] in tracks]
+            playlists[playlist.get("Name") or "Untitled"] = items
+        return list(tracks.values()), playlists
+
+    def write(self, tracks, playlists):
+        # tracks 为 metadata_get 的结果加上 audio、duration 和 size, playlists 为 {名称: 路径列表}
+        ids = {}
+        library_tracks = {}
+        for track_id, track in enumerate(tracks, 1):
+            ids[os.path.abspath(track["audio"])] = track_id
+            entry = {
+                "Track ID": track_id,
+                "Name": track.get("title") or os.path.splitext(os.path.basename(track["audio"]))[0],
+                "Kind": f"{os.path.splitext(track['audio'])[1].lstrip('.').upper()} audio file",
+                "Location": "file://localhost" + pathlib.Path(os.path.abspath(track["audio"])).as_uri()[len("file://"):],
+            }
+            for key, name in [("Artist", "artist"), ("Album", "album"), ("Album Artist", "albumartist"),
+                              ("Genre", "genre"), ("Composer", "composer"), ("Year", "year"),
+                              ("Track Number", "tracknumber"), ("Disc Number", "discnumber"),
+                              ("BPM", "bpm"), ("Total Time", "duration"), ("Size", "size")]:
+                if track.get(name):
+                    entry[key] = track[name]
+            if track.get("rating"):
+                entry["Rating"] = track["rating"] * 20
+            library_tracks[str(track_id)] = entry
+        # 第一个播放列表为包含所有歌曲的资料库
+        playlists = {"Library": [track["audio"] for track in tracks], **playlists}
+        library_playlists = [{
+            "Name": name,
+            "Playlist ID": playlist_id,
+            "All Items": True,
+            **({"Master": True, "Visible": False} if playlist_id == len(tracks) + 1 else {}),
+            "Playlist Items": [{"Track ID": ids[os.path.abspath(audio)]} for audio in items if os.path.abspath(audio) in ids],
+        } for playlist_id, (name, items) in enumerate(playlists.items(), len(tracks) + 1)]
+        with open(self.library, 'wb') as f:
+            plistlib.dump({
+                "Major Version": 1,
+                "Minor Version": 1,
+                "Application Version": "12.0",
+                "Tracks": library_tracks,
+                "Playlists": library_playlists,
+            }, f, sort_keys=False)
@@ -0,0 +1,49 @@
+import math
+import re
+import subprocess
+
+REFERENCE_LOUDNESS = -18.0
+
+
+class LoudnessProcessing():
+
+    def __init__(self, ffmpeg="ffmpeg") -> None:
+        self.ffmpeg = ffmpeg
+
+    @staticmethod
+    def __last_value(pattern, output):
+        values = re.findall(pattern, output)
+        if not values or values[-1] == "-inf":
+            return None
+        return float(values[-1])
+
+    def measure(self, audio):
+        # 使用 ffmpeg 的 ebur128 滤镜, 最后输出的 Summary 即整首的测量结果
+        cmd = [self.ffmpeg, '-hide_banner', '-nostats',
+               '-i', audio,
+               '-af', 'ebur128=peak=true',
+               '-f', 'null', '-']
+        result = subprocess.run(cmd, capture_output=True, text=True, encoding='utf-8', errors='replace')
+        if result.returncode != 0:
+            raise RuntimeError(result.stderr.strip().splitlines()[-1] if result.stderr.strip() else "ffmpeg failed")
+        return {
+            "loudness": self.__last_value(r'I:\s+(-?[\d.]+|-inf) LUFS', result.stderr),
+            "range": self.__last_value(r'LRA:\s+(-?[\d.]+) LU\b', result.stderr),
+            "peak": self.__last_value(r'Peak:\s+(-?[\d.]+|-inf) dBFS', result.stderr),
+        }
+
+    @staticmethod
+    def track_gain(measurement):
+        gain = REFERENCE_LOUDNESS - measurement["loudness"] if measurement["loudness"] is not None else None
+        peak = 10 ** (measurement["peak"] / 20) if measurement["peak"] is not None else 0.0
+        return gain, peak
+
+    @staticmethod
+    def album_gain(measurements, durations):
+        # 按时长对各音轨的响度做能量平均, 近似整张专辑的响度(忽略了跨音轨的门限)
+        weighted = [(10 ** (m["loudness"] / 10), d) for m, d in zip(measurements, durations) if m["loudness"] is not None]
+        if not weighted:
+            return None, 0.0
+        loudness = 10 * math.log10(sum(energy * duration for energy, duration in weighted) / sum(duration for _, duration in weighted))
+        peak = max((10 ** (m["peak"] / 20) for m in measurements if m["peak"] is not None), default=0.0)
+        return REFERENCE_LOUDNESS - loudness, peak
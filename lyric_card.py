@@ -0,0 +1,76 @@
+from PIL import Image, ImageDraw, ImageFilter, ImageFont
+import io
+import os
+
+# 常见系统中支持中日文的字体, 都找不到时使用 Pillow 自带的字体(不支持中日文)
+CARD_FONTS = [
+    "C:/Windows/Fonts/msyh.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+]
+
+
+class LyricCard():
+
+    def __init__(self, lines, title=None, artist=None, cover=None, font=None, width=1080) -> None:
+        self.lines = lines
+        self.title = title
+        self.artist = artist
+        self.cover = cover
+        self.font = font or next((path for path in CARD_FONTS if os.path.isfile(path)), None)
+        self.width = width
+
+    def __font(self, size):
+        return ImageFont.truetype(self.font, size) if self.font else ImageFont.load_default(size)
+
+    @staticmethod
+    def __wrap(draw, text, font, width):
+        # 按字符折行, 中日文没有空格也能正确换行, 英文尽量在空格处断开
+        rows, row = [], ""
+        for char in text:
+            if draw.textlength(row + char, font=font) <= width:
+                row += char
+                continue
+            space = row.rfind(" ")
+            if char != " " and space > 0:
+                rows.append(row[:space])
+                row = row[space + 1:] + char
+            else:
+                rows.append(row)
+                row = char.lstrip()
+        return rows + [row] if row else rows
+
+    def render(self, output):
+        margin = self.width // 12
+        cover = Image.open(io.BytesIO(self.cover)).convert("RGB") if self.cover else None
+        lyric_font, title_font, artist_font = self.__font(self.width // 18), self.__font(self.width // 26), self.__font(self.width // 32)
+        measure = ImageDraw.Draw(Image.new("RGB", (1, 1)))
+        rows = [row for line in self.lines for row in self.__wrap(measure, line, lyric_font, self.width - margin * 2)]
+        line_height = int(lyric_font.size * 1.6)
+        cover_size = self.width // 5
+        height = margin * 3 + cover_size + line_height * len(rows)
+        # 背景为放大模糊并压暗的封面, 没有封面时为纯色
+        if cover:
+            scale = max(self.width / cover.width, height / cover.height)
+            background = cover.resize((int(cover.width * scale) + 1, int(cover.height * scale) + 1))
+            background = background.crop((0, 0, self.width, height)).filter(ImageFilter.GaussianBlur(self.width // 20))
+            background = Image.blend(background, Image.new("RGB", background.size, (0, 0, 0)), 0.55)
+        else:
+            background = Image.new("RGB", (self.width, height), (32, 32, 40))
+        draw = ImageDraw.Draw(background)
+        y = margin
+        for row in rows:
+            draw.text((margin, y), row, font=lyric_font, fill=(255, 255, 255))
+            y += line_height
+        y += margin
+        text_x = margin
+        if cover:
+            background.paste(cover.resize((cover_size, cover_size)), (margin, y))
+            text_x += cover_size + margin // 2
+        draw.text((text_x, y + cover_size // 2 - title_font.size), self.title or "", font=title_font, fill=(255, 255, 255))
+        draw.text((text_x, y + cover_size // 2 + artist_font.size // 4), self.artist or "", font=artist_font, fill=(200, 200, 200))
+        background.save(output, format="PNG")
+        return output
@@ -0,0 +1,103 @@
+from utils.storage import backup_list, save_json
+import json
+import os
+import re
+
+
+class LyricsHistory():
+
+    def __init__(self, path="lyrics_history.json", limit=20, backups=5) -> None:
+        self.path = path
+        self.limit = limit
+        self.backups = backups
+        self.backed_up = False
+        self.history = self.__load()
+
+    def __load(self):
+        if not os.path.isfile(self.path):
+            return {}
+        with open(self.path, 'r', encoding='utf-8', errors='replace') as f:
+            text = f.read()
+        try:
+            return json.loads(text)
+        except ValueError:
+            return self.__salvage(text)
+
+    def __salvage(self, text):
+        # 文件损坏时逐条解析还能读出的记录, 原文件改名保留
+        history = {}
+        decoder = json.JSONDecoder()
+        for match in re.finditer(r'"((?:[^"\\]|\\.)*)"\s*:\s*(?=\{\s*"undo")', text):
+            try:
+                entry, end = decoder.raw_decode(text, match.end())
+                history[json.loads('"' + match.group(1) + '"')] = {"undo": entry["undo"], "redo": entry["redo"]}
+            except (ValueError, KeyError):
+                continue
+        salvaged = len(history)
+        # 逐条解析不出的文件再从最新的可用备份中补上
+        restored, source = 0, None
+        for backup in backup_list(self.path):
+            try:
+                with open(backup, 'r', encoding='utf-8') as f:
+                    entries = json.load(f)
+            except ValueError:
+                continue
+            source = backup
+            for audio, entry in entries.items():
+                if audio not in history:
+                    history[audio] = entry
+                    restored += 1
+            break
+        lost = max(text.count('"undo"') - salvaged - restored, 0)
+        # 不覆盖之前保留的损坏文件
+        corrupt, index = self.path + ".corrupt", 1
+        while os.path.exists(corrupt):
+            corrupt, index = f"{self.path}.corrupt.{index}", index + 1
+        os.replace(self.path, corrupt)
+        # 立即写回恢复的记录, 否则下次运行时历史记录就是空的
+        save_json(self.path, history, indent=2)
+        print(f"Error: {self.path} is corrupted, recovered {salvaged} file(s)"
+              + (f" and {restored} from {source}" if source else "") + f", lost {lost}, original kept as {corrupt}")
+        return history
+
+    def __save(self):
+        # 每次运行只在第一次写入前备份, 批量处理时不会很快把旧备份挤掉
+        save_json(self.path, self.history, backups=0 if self.backed_up else self.backups, indent=2)
+        self.backed_up = True
+
+    def backup_list(self):
+        return backup_list(self.path)
+
+    def restore(self, backup=None):
+        # backup 为空时恢复最新的备份, 恢复前的文件同样会先备份
+        backup = backup or next(iter(self.backup_list()), None)
+        if backup is None:
+            raise FileNotFoundError(f"no backup of {self.path}")
+        with open(backup, 'r', encoding='utf-8') as f:
+            self.history = json.load(f)
+        self.__save()
+        return backup
+
+    def __entry(self, audio):
+        return self.history.setdefault(os.path.abspath(audio), {"undo": [], "redo": []})
+
+    def record(self, audio, before, after):
+        entry = self.__entry(audio)
+        entry["undo"] = (entry["undo"] + [[before, after]])[-self.limit:]
+        entry["redo"] = []
+        self.__save()
+
+    def undo(self, audio):
+        # 返回需要恢复的歌词, None 表示恢复为没有歌词; 没有可撤销的记录时抛出 IndexError
+        entry = self.__entry(audio)
+        before, after = entry["undo"].pop()
+        entry["redo"].append([before, after])
+        self.__save()
+        return before
+
+    def redo(self, audio):
+        entry = self.__entry(audio)
+        before, after = entry["redo"].pop()
+        entry["undo"].append([before, after])
+        self.__save()
+        return after
@@ -0,0 +1,368 @@
+from charset_normalizer import from_bytes
+import xml.etree.ElementTree as ET
+import codecs
+import os
+import re
+import zlib
+
+TTML_NS = '{http://www.w3.org/ns/ttml}'
+TTM_NS = '{http://www.w3.org/ns/ttml#metadata}'
+XML_NS = '{http://www.w3.org/XML/1998/namespace}'
+TTML_START = re.compile(r'\s*(<\?xml|<tt[\s>:])')
+
+LRC_TIME = re.compile(r'\[(\d+):(\d+(?:[.:]\d+)?)\]')
+LRC_TAG = re.compile(r'^\[([a-zA-Z#]+):(.*)\]$')
+LRC_WORD = re.compile(r'<(\d+):(\d+(?:[.:]\d+)?)>')
+# 罗马音行与原文使用相同的时间标签, 用前缀与翻译行区分
+ROMANIZATION_MARK = '[ro]'
+
+BOMS = [
+    (codecs.BOM_UTF8, 'utf-8'),
+    (codecs.BOM_UTF16_LE, 'utf-16-le'),
+    (codecs.BOM_UTF16_BE, 'utf-16-be'),
+]
+LEGACY_ENCODINGS = ['gb18030', 'big5', 'shift_jis', 'euc_kr']
+
+INSTRUMENTAL = re.compile(r'纯音乐|純音樂|伴奏|instrumental|inst\.?\)|off vocal|karaoke', re.IGNORECASE)
+
+CREDIT_PATTERNS = [
+    r'^\s*(作词|作曲|编曲|词|曲|制作人|监制|混音|母带|和声|吉他|贝斯|鼓|弦乐|录音|出品|发行|OP|SP|'
+    r'lyrics?( by)?|lyricist|written by|composer|composed by|arranged by|producer|produced by|mixed by|mastered by)\s*[:：]',
+    r'^\s*(lyrics|composed|arranged|produced|mixed|mastered) by\b',
+    r'TME享有本翻译作品的著作权|未经(著作权人)?许可.*不得|QQ音乐|酷狗音乐|网易云音乐|以下歌词翻译由文曲大模型提供',
+    r'^\s*[(（\[【]\s*[)）\]】]\s*$',
+]
+
+KANA = re.compile(r'[\u3040-\u30ff]')
+HANZI = re.compile(r'[\u4e00-\u9fff]')
+
+KRC_KEY = bytes([64, 71, 97, 119, 94, 50, 116, 71, 81, 54, 49, 45, 206, 210, 110, 105])
+KRC_LINE = re.compile(r'^\[(\d+),(\d+)\](.*)$')
+KRC_WORD = re.compile(r'<(\d+),(\d+),\d+>')
+
+
+class LyricsProcessing():
+
+    def __init__(self, lyrics=None) -> None:
+        self.lyrics = lyrics
+        self.tags = {}
+        self.agents = {}
+        self.lines = self.__parse()
+
+    def __read(self):
+        if isinstance(self.lyrics, bytes):
+            data = self.lyrics
+        elif os.path.isfile(self.lyrics):
+            with open(self.lyrics, 'rb') as f:
+                data = f.read()
+        else:
+            return self.lyrics
+        if data.startswith(b'krc1'):
+            data = self.krc_decrypt(data)
+        return self.decode(data)
+
+    @staticmethod
+    def decode(data):
+        # 旧的 lrc 文件常见 GBK/Big5/Shift-JIS 编码, 先看 BOM, 再试 utf-8, 最后猜测编码
+        for bom, encoding in BOMS:
+            if data.startswith(bom):
+                return data[len(bom):].decode(encoding)
+        try:
+            return data.decode('utf-8')
+        except UnicodeDecodeError:
+            pass
+        best = from_bytes(data, cp_isolation=LEGACY_ENCODINGS).best()
+        if best is not None:
+            return str(best)
+        return data.decode('gb18030', errors='replace')
+
+    def __parse(self):
+        if self.lyrics is None:
+            return []
+        text = self.__read()
+        # 只有 xml 声明或 <tt 根元素才按 ttml 解析, "<3" 这样开头的普通歌词仍按 lrc 处理
+        if TTML_START.match(text):
+            try:
+                return self.__parse_ttml(text)
+            except ET.ParseError:
+                pass
+        if any(KRC_LINE.match(raw.strip()) for raw in text.splitlines()):
+            return self.__parse_krc(text)
+        return self.__parse_lrc(text)
+
+    @staticmethod
+    def krc_decrypt(data):
+        # krc 文件: 4 字节 krc1 文件头 + 异或加密的 zlib 数据
+        data = bytes(byte ^ KRC_KEY[index % len(KRC_KEY)] for index, byte in enumerate(data[4:]))
+        return zlib.decompress(data)
+
+    @staticmethod
+    def __lrc_time(minute, second):
+        return int(round((int(minute) * 60 + float(second.replace(':', '.'))) * 1000))
+
+    @staticmethod
+    def __ttml_time(value):
+        # 支持 hh:mm:ss.fff / mm:ss.fff / 12.3s / 123ms / 12.3 几种写法
+        if value is None:
+            return None
+        value = value.strip()
+        if value.endswith('ms'):
+            return int(round(float(value[:-2])))
+        if value.endswith('s'):
+            return int(round(float(value[:-1]) * 1000))
+        seconds = 0.0
+        for part in value.split(':'):
+            seconds = seconds * 60 + float(part)
+        return int(round(seconds * 1000))
+
+    def __parse_lrc(self, text):
+        lines = []
+        for number, raw in enumerate(text.splitlines()):
+            raw = raw.strip()
+            tag = LRC_TAG.match(raw)
+            if tag and not LRC_TIME.match(raw):
+                self.tags[tag.group(1)] = tag.group(2).strip()
+                continue
+            times = []
+            while LRC_TIME.match(raw):
+                stamp = LRC_TIME.match(raw)
+                times.append(self.__lrc_time(stamp.group(1), stamp.group(2)))
+                raw = raw[stamp.end():]
+            words = []
+            marks = list(LRC_WORD.finditer(raw))
+            for index, mark in enumerate(marks):
+                end = marks[index + 1].start() if index + 1 < len(marks) else len(raw)
+                word = raw[mark.end():end]
+                if word:
+                    words.append({'time': self.__lrc_time(mark.group(1), mark.group(2)), 'text': word})
+            content = LRC_WORD.sub('', raw)
+            romanized = bool(times) and content.startswith(ROMANIZATION_MARK)
+            content = content[len(ROMANIZATION_MARK):] if romanized else content
+            for time in times or [None]:
+                lines.append((romanized, {'time': time, 'end': None, 'text': content, 'words': words, 'index': number,
+                                          'agent': None, 'background': False, 'translation': None, 'romanization': None}))
+        lines.sort(key=lambda item: -1 if item[1]['time'] is None else item[1]['time'])
+        # 双语 lrc 中时间相同的第二行是上一行的翻译, 带 [ro] 前缀的是上一行的罗马音
+        merged = []
+        for romanized, line in lines:
+            previous = merged[-1] if merged else None
+            if romanized:
+                if previous and previous['time'] == line['time'] and previous['romanization'] is None:
+                    previous['romanization'] = line['text']
+                continue
+            if previous and line['time'] is not None and previous['time'] == line['time'] \
+                    and previous['index'] + 1 == line['index'] \
+                    and previous['translation'] is None and line['text'].strip():
+                previous['translation'] = line['text']
+            else:
+                merged.append(line)
+        return merged
+
+    def __parse_krc(self, text):
+        lines = []
+        for raw in text.splitlines():
+            raw = raw.strip()
+            line = KRC_LINE.match(raw)
+            if line is None:
+                tag = LRC_TAG.match(raw)
+                if tag:
+                    self.tags[tag.group(1)] = tag.group(2).strip()
+                continue
+            begin, duration = int(line.group(1)), int(line.group(2))
+            words = []
+            marks = list(KRC_WORD.finditer(line.group(3)))
+            for index, mark in enumerate(marks):
+                end = marks[index + 1].start() if index + 1 < len(marks) else len(line.group(3))
+                # krc 的逐字时间是相对行首的偏移
+                offset, length = int(mark.group(1)), int(mark.group(2))
+                words.append({'time': begin + offset, 'end': begin + offset + length,
+                              'text': line.group(3)[mark.end():end]})
+            lines.append({'time': begin, 'end': begin + duration, 'text': KRC_WORD.sub('', line.group(3)),
+                          'words': words, 'agent': None, 'background': False, 'translation': None, 'romanization': None})
+        # language 标签是 base64 编码的翻译数据, 不属于 lrc 的元数据
+        self.tags.pop('language', None)
+        return lines
+
+    def __ttml_words(self, element):
+        words = []
+        background = []
+        for span in element.findall(TTML_NS + 'span'):
+            if span.get(TTM_NS + 'role') == 'x-bg':
+                background.append(span)
+                continue
+            if span.get(TTM_NS + 'role') is not None:
+                continue
+            text = ''.join(span.itertext()) + (span.tail or '')
+            if span.get('begin') is None and words:
+                words[-1]['text'] += text
+                continue
+            words.append({'time': self.__ttml_time(span.get('begin')),
+                          'end': self.__ttml_time(span.get('end')), 'text': text})
+        if not words and element.text and element.text.strip():
+            words.append({'time': self.__ttml_time(element.get('begin')),
+                          'end': self.__ttml_time(element.get('end')), 'text': element.text})
+        return words, background
+
+    def __ttml_line(self, element, agent, is_background):
+        words, background = self.__ttml_words(element)
+        text = ''.join(word['text'] for word in words).strip()
+        begin = self.__ttml_time(element.get('begin'))
+        line = {
+            'time': begin if begin is not None else (words[0]['time'] if words else None),
+            'end': self.__ttml_time(element.get('end')),
+            'text': text,
+            # 逐字时间只在 span 上有 begin 时才有意义, 整行歌词不保留 words
+            'words': words if len(words) > 1 or element.find(TTML_NS + 'span') is not None else [],
+            'agent': agent,
+            'background': is_background,
+            'translation': None,
+            'romanization': None,
+        }
+        lines = [line]
+        for span in background:
+            lines.extend(self.__ttml_line(span, agent, True))
+        return lines
+
+    def __parse_ttml(self, text):
+        root = ET.fromstring(text)
+        for agent in root.iter(TTM_NS + 'agent'):
+            name = agent.find(TTM_NS + 'name')
+            self.agents[agent.get(XML_NS + 'id')] = name.text if name is not None else agent.get(XML_NS + 'id')
+        for title in root.iter(TTM_NS + 'title'):
+            self.tags.setdefault('ti', title.text)
+        lines = []
+        for paragraph in root.iter(TTML_NS + 'p'):
+            lines.extend(self.__ttml_line(paragraph, paragraph.get(TTM_NS + 'agent'), False))
+        return [line for line in lines if line['text']]
+
+    def is_instrumental(self):
+        # 纯音乐的在线歌词通常只有一两行"此歌曲为没有填词的纯音乐，请您欣赏"之类的提示
+        texts = [line['text'] for line in self.lines if line['text'].strip()]
+        return len(texts) <= 2 and any(INSTRUMENTAL.search(text) for text in texts)
+
+    def clean(self, patterns=None):
+        # 删除作词作曲等制作信息、平台水印和空括号行, patterns 为额外的正则
+        patterns = [re.compile(pattern, re.IGNORECASE) for pattern in CREDIT_PATTERNS + (patterns or [])]
+        self.lines = [line for line in self.lines if not any(pattern.search(line['text']) for pattern in patterns)]
+        return self
+
+    def attach_translation(self, translation):
+        track = translation if isinstance(translation, LyricsProcessing) else LyricsProcessing(translation)
+        texts = {line['time']: line['text'] for line in track.lines if line['time'] is not None}
+        for line in self.lines:
+            text = texts.get(line['time'])
+            # qq音乐的翻译用 // 占位没有翻译的行
+            line['translation'] = text if text and text.strip() not in ('', '//') else None
+        return self
+
+    def detach_translation(self):
+        for line in self.lines:
+            line['translation'] = None
+        return self
+
+    def romanize(self):
+        # 整首歌中出现假名时按日语处理(只有汉字的行也用日语读音), 否则含汉字的行按中文处理
+        from pypinyin import lazy_pinyin, Style
+        import pykakasi
+        kakasi = pykakasi.kakasi()
+        japanese = any(KANA.search(line['text']) for line in self.lines)
+        for line in self.lines:
+            if japanese and (KANA.search(line['text']) or HANZI.search(line['text'])):
+                line['romanization'] = ' '.join(item['hepburn'] for item in kakasi.convert(line['text']) if item['hepburn'].strip())
+            elif HANZI.search(line['text']):
+                line['romanization'] = ' '.join(word.strip() for word in lazy_pinyin(line['text'], style=Style.TONE) if word.strip())
+        return self
+
+    def validate(self, duration=None, gap=30000):
+        # duration 为音频时长(毫秒), 返回的问题按歌词顺序排列
+        problems = []
+        timed = [line for line in self.lines if line['time'] is not None]
+        indexes = [line.get('index') for line in timed]
+        latest = None
+        previous = None
+        for line in timed:
+            index = line.get('index')
+            # 同一行有多个时间标签(重复的副歌)时不检查顺序
+            if index is not None and indexes.count(index) == 1:
+                if latest is not None and index < latest:
+                    problems.append({'time': line['time'], 'type': 'order', 'text': line['text'],
+                                     'message': 'timestamp is out of order in the source'})
+                latest = index if latest is None else max(latest, index)
+            if duration is not None and line['time'] > duration:
+                problems.append({'time': line['time'], 'type': 'duration', 'text': line['text'],
+                                 'message': 'timestamp is beyond the track duration'})
+            if previous is not None and previous['time'] == line['time']:
+                problems.append({'time': line['time'], 'type': 'duplicate', 'text': line['text'],
+                                 'message': 'timestamp is used by more than one line'})
+            if previous is not None and line['time'] - previous['time'] > gap:
+                problems.append({'time': line['time'], 'type': 'gap', 'text': line['text'],
+                                 'message': 'gap of {}s since the previous line'.format((line['time'] - previous['time']) // 1000)})
+            if not line['text'].strip():
+                problems.append({'time': line['time'], 'type': 'empty', 'text': line['text'],
+                                 'message': 'line is empty'})
+            previous = line
+        if timed and len(timed) != len(self.lines):
+            for line in self.lines:
+                if line['time'] is None and line['text'].strip():
+                    problems.append({'time': None, 'type': 'unsynced', 'text': line['text'],
+                                     'message': 'line has no timestamp'})
+        return problems
+
+    @staticmethod
+    def format_time(ms, bracket='[]'):
+        minute, rest = divmod(max(ms, 0), 60000)
+        return '{}{:02d}:{:02d}.{:02d}{}'.format(bracket[0], minute, rest // 1000, rest % 1000 // 10, bracket[1])
+
+    def to_plain(self):
+        return '\n'.join(line['text'] for line in self.lines)
+
+    def to_lrc(self, words=True, translation=True, romanization=True, tags=True):
+        output = ['[{}:{}]'.format(key, value) for key, value in self.tags.items()] if tags else []
+        agents = {line['agent'] for line in self.lines if line['agent']}
+        for line in self.lines:
+            prefix = '' if line['time'] is None else self.format_time(line['time'])
+            if words and line['words'] and line['words'][0]['time'] is not None:
+                content = ''.join(self.format_time(word['time'], '<>') + word['text'] for word in line['words'])
+                if line['words'][-1].get('end') is not None:
+                    content += self.format_time(line['words'][-1]['end'], '<>')
+            else:
+                content = line['text']
+            if line['background'] and not line['text'].startswith('('):
+                content = '(' + content.strip() + ')'
+            elif len(agents) > 1 and line['agent'] and not line['background']:
+                content = self.agents.get(line['agent'], line['agent']) + ': ' + content
+            output.append(prefix + content)
+            if translation and line['translation'] and line['time'] is not None:
+                output.append(prefix + line['translation'])
+            if romanization and line['romanization'] and line['time'] is not None:
+                output.append(prefix + ROMANIZATION_MARK + line['romanization'])
+        return '\n'.join(output)
+
+
+class LyricsSync():
+
+    def __init__(self, lyrics) -> None:
+        self.track = lyrics if isinstance(lyrics, LyricsProcessing) else LyricsProcessing(lyrics)
+        self.lines = [line for line in self.track.lines if line['text'].strip()]
+        self.times = []
+
+    @property
+    def next_line(self):
+        return self.lines[len(self.times)]['text'] if len(self.times) < len(self.lines) else None
+
+    def tap(self, position):
+        # position 为播放位置(毫秒), 早于上一次点击的时间会被抬到上一次的位置
+        if self.next_line is None:
+            return None
+        self.times.append(max(position, self.times[-1]) if self.times else max(position, 0))
+        return self.times[-1]
+
+    def undo(self):
+        return self.times.pop() if self.times else None
+
+    def finalize(self):
+        for line, time in zip(self.lines, self.times):
+            line['time'] = time
+            line['words'] = []
+        self.track.lines = self.lines[:len(self.times)]
+        return self.track
@@ -1,26 +1,142 @@
-from mutagen import flac, id3, File
+from mutagen import flac, id3, mp4, File
+from PIL import Image
+from charset_normalizer import from_bytes
+from lyrics_processing import LyricsProcessing
+import io
 import os
+import re
+
+ID3_KEYS = {
+    "title": "TIT2",
+    "artist": "TPE1",
+    "album": "TALB",
+    "albumartist": "TPE2",
+    "tracknumber": "TRCK",
+    "discnumber": "TPOS",
+    "year": "TDRC",
+    "genre": "TCON",
+    "composer": "TCOM",
+    "bpm": "TBPM",
+    "key": "TKEY",
+}
+VORBIS_KEYS = {
+    "title": "TITLE",
+    "artist": "ARTIST",
+    "album": "ALBUM",
+    "albumartist": "ALBUMARTIST",
+    "tracknumber": "TRACKNUMBER",
+    "discnumber": "DISCNUMBER",
+    "year": "DATE",
+    "genre": "GENRE",
+    "composer": "COMPOSER",
+    "bpm": "BPM",
+    "key": "INITIALKEY",
+}
+MP4_KEYS = {
+    "title": "\xa9nam",
+    "artist": "\xa9ART",
+    "album": "\xa9alb",
+    "albumartist": "aART",
+    "tracknumber": "trkn",
+    "discnumber": "disk",
+    "year": "\xa9day",
+    "genre": "\xa9gen",
+    "composer": "\xa9wrt",
+    "bpm": "tmpo",
+    "key": "----:com.apple.iTunes:initialkey",
+}
+REPLAYGAIN_KEYS = [
+    "replaygain_track_gain",
+    "replaygain_track_peak",
+    "replaygain_album_gain",
+    "replaygain_album_peak",
+    "r128_track_gain",
+    "r128_album_gain",
+]
+MUSICBRAINZ_KEYS = {
+    "musicbrainz_albumid": "MusicBrainz Album Id",
+    "musicbrainz_artistid": "MusicBrainz Artist Id",
+}
+VALUE_SEPARATORS = re.compile(r'\s*[/;、\x00]\s*')
+# POPM 中 1~5 星对应的字节值, 与 Windows Media Player/MusicBee 一致
+POPM_RATINGS = [0, 1, 64, 128, 196, 255]
+POPM_EMAIL = "Windows Media Player 9 Series"
+FILENAME_PATTERNS = [
+    "%artist% - %title%",
+    "%track%. %title%",
+    "%track% - %title%",
+    "%track% %title%",
+]
+# 乱码标签绝大多数是中文或日文, 短文本加入 euc_kr 容易误判
+TAG_ENCODINGS = ["gb18030", "big5", "shift_jis"]
+# 默认模式能补充标题、歌手、歌词和封面的格式, 其他格式只支持读取
+TAGGABLE_FORMATS = ["audio/mp3", "audio/flac"]
 
 class AudioProcessing():
 
-    def __init__(self, audio=None, title=None, artist=None, lyrics=None, cover=None) -> None:
+    def __init__(self, audio=None, title=None, artist=None, lyrics=None, cover=None, cover_size=1000) -> None:
         self.audio = audio
         self.title = title
         self.artist = artist
         self.lyrics = lyrics
         self.cover = cover
+        self.cover_size = cover_size
         self.audio_format = self.__get_audio_format()
 
-    def __file_type_check(self, file):
+    def __file_type_check(self, file, binary=False):
         if file is None:
             return None
         if os.path.isfile(file):
-            with open(file, 'r') as f:
+            with open(file, 'rb') as f:
                 file_stream = f.read()
-            return file_stream
+            return file_stream if binary else LyricsProcessing.decode(file_stream)
         else:
             return file
 
+    def __cover_prepare(self, cover):
+        # 统一转为不超过 cover_size 的 jpeg, 返回 (数据, 宽, 高)
+        image = Image.open(io.BytesIO(self.__file_type_check(cover, binary=True)))
+        if image.format == "JPEG" and max(image.size) <= self.cover_size and image.mode == "RGB":
+            return self.__file_type_check(cover, binary=True), image.width, image.height
+        image = image.convert("RGB")
+        image.thumbnail((self.cover_size, self.cover_size))
+        data = io.BytesIO()
+        image.save(data, format="JPEG", quality=90)
+        return data.getvalue(), image.width, image.height
+
+    @staticmethod
+    def filename_parse(audio, patterns=None):
+        # 按顺序尝试 %artist% - %title% 这类模式, 返回第一个匹配的字段
+        stem = os.path.splitext(os.path.basename(audio))[0]
+        for pattern in patterns or FILENAME_PATTERNS:
+            regex = ""
+            for part in re.split(r'(%\w+%)', pattern):
+                if part == "%track%":
+                    regex += r'(?P<tracknumber>\d+)'
+                elif re.fullmatch(r'%\w+%', part):
+                    regex += r'(?P<{}>.+?)'.format(part.strip('%'))
+                else:
+                    regex += re.escape(part)
+            match = re.fullmatch(regex, stem.strip())
+            if match:
+                return {name: value.strip() for name, value in match.groupdict().items()}
+        return {}
+
+    def path_format(self, pattern):
+        # 按 %artist%/%album%/%track% - %title% 这类模式生成相对路径, 保留原扩展名
+        metadata = self.metadata_get()
+        defaults = {"artist": "Unknown Artist", "albumartist": metadata["artist"] or "Unknown Artist",
+                    "album": "Unknown Album", "title": os.path.splitext(os.path.basename(self.audio))[0]}
+        def replace(match):
+            name = "tracknumber" if match.group(1) == "track" else match.group(1)
+            value = metadata.get(name) or defaults.get(name, "")
+            if name in ["tracknumber", "discnumber"] and value:
+                value = f"{value:02d}"
+            # 去掉文件名中不允许的字符
+            return re.sub(r'[<>:"/\\|?*\x00-\x1f]', "_", str(value)).strip(" .") or "_"
+        parts = [re.sub(r'%(\w+)%', replace, part) for part in pattern.split("/")]
+        return os.path.join(*parts) + os.path.splitext(self.audio)[1]
+
     def __get_audio_format(self):
         try:
             audio = File(self.audio, easy=True)
@@ -30,70 +146,103 @@ class AudioProcessing():
             print(f"Error: {e}")
             return None
         
-    def __modify_mp3_metadata(self):
+    def __load_id3(self):
         try:
-            audio = id3.ID3(self.audio)
-        except:
-            audio = id3.ID3()
-            self.__modify_mp3_metadata()
-        audio["TIT2"] = id3.TIT2(encoding=3, text=self.title) if audio.get("TIT2") is None and self.title is not None else audio["TIT2"]
-        audio["TPE1"] = id3.TPE1(encoding=3, text=self.artist) if audio.get("TPE1") is None and self.artist is not None else audio['TPE1']
-        if self.__file_type_check(self.lyrics) is not None:
-            audio.add(id3.TXXX(encoding=3, desc="Lyrics", text=self.__file_type_check(self.lyrics))) if audio.get("TXXX") is None else audio['TXXX']
+            return id3.ID3(self.audio)
+        except id3.ID3NoHeaderError:
+            return id3.ID3()
+
+    def __modify_mp3_metadata(self):
+        audio = self.__load_id3()
+        if "TIT2" not in audio and self.title is not None:
+            audio.add(id3.TIT2(encoding=3, text=self.title))
+        if "TPE1" not in audio and self.artist is not None:
+            audio.add(id3.TPE1(encoding=3, text=self.artist))
+        lyrics = self.__file_type_check(self.lyrics)
+        if lyrics is not None and not audio.getall("TXXX:Lyrics") and not audio.getall("USLT"):
+            audio.add(id3.TXXX(encoding=3, desc="Lyrics", text=lyrics))
         if not audio.getall("APIC") and self.cover is not None:
-            audio["APIC"] = id3.APIC(encoding=3, mime='image/jpeg', type=3, desc=u'Cover', data=self.__file_type_check(self.cover))
-        return audio.save()
+            data, width, height = self.__cover_prepare(self.cover)
+            audio.add(id3.APIC(encoding=3, mime='image/jpeg', type=3, desc=u'Cover', data=data))
+        return audio.save(self.audio)
 
     def __modify_flac_metadata(self):
-        try:
-            audio = flac.FLAC(self.audio)
-        except:
-            audio = flac.FLAC()
-            self.__modify_flac_metadata()
-        audio["TITLE"] = self.title if audio.get("TITLE") is None and self.title is not None else audio["TITLE"]
-        audio["ARTIST"] = self.artist if audio.get("ARTIST") is None and self.title is not None else audio["ARTIST"]
-        if self.__file_type_check(self.lyrics) is not None:
-            audio["LYRICS"] = self.__file_type_check(self.lyrics) if audio.get("LYRICS") is None else audio["LYRICS"]
+        audio = flac.FLAC(self.audio)
+        if "TITLE" not in audio and self.title is not None:
+            audio["TITLE"] = self.title
+        if "ARTIST" not in audio and self.artist is not None:
+            audio["ARTIST"] = self.artist
+        lyrics = self.__file_type_check(self.lyrics)
+        if lyrics is not None and "LYRICS" not in audio and "UNSYNCEDLYRICS" not in audio:
+            audio["LYRICS"] = lyrics
         existing_covers = audio.pictures
         if not existing_covers and self.cover is not None:
-            image = flac.Picture()
-            image.data = self.__file_type_check(self.cover)
-            image.type = 3
-            image.mime = u"image/jpeg"
-            image.width = 500
-            image.height = 500
-            audio.add_picture(image)
+            audio.add_picture(self.__flac_picture(self.cover))
         return audio.save()
 
+    def __flac_picture(self, cover):
+        image = flac.Picture()
+        image.data, image.width, image.height = self.__cover_prepare(cover)
+        image.type = 3
+        image.mime = u"image/jpeg"
+        image.depth = 24
+        return image
+
+    def cover_set(self, cover):
+        # 替换已有的封面(front cover), 其他类型的图片保留
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            data, width, height = self.__cover_prepare(cover)
+            for frame in audio.getall("APIC"):
+                if frame.type == 3:
+                    audio.delall(frame.HashKey)
+            audio.add(id3.APIC(encoding=3, mime='image/jpeg', type=3, desc=u'Cover', data=data))
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            pictures = [picture for picture in audio.pictures if picture.type != 3]
+            audio.clear_pictures()
+            for picture in [self.__flac_picture(cover)] + pictures:
+                audio.add_picture(picture)
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def cover_get(self):
+        # 优先返回封面(front cover), 没有时返回第一张图片
+        if self.audio_format == "audio/mp3":
+            pictures = sorted(self.__load_id3().getall("APIC"), key=lambda frame: frame.type != 3)
+        elif self.audio_format == "audio/flac":
+            pictures = sorted(flac.FLAC(self.audio).pictures, key=lambda picture: picture.type != 3)
+        elif self.audio_format == "audio/mp4":
+            pictures = (mp4.MP4(self.audio).tags or {}).get("covr", [])
+            return bytes(pictures[0]) if pictures else None
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+        return pictures[0].data if pictures else None
+
+
     def metadata_processing(self):
         if self.audio_format == "audio/mp3":
             self.__modify_mp3_metadata()
         elif self.audio_format == "audio/flac":
             self.__modify_flac_metadata()
         else:
-            raise "不支持的音频格式或文件输入路径有误"
+            raise ValueError("不支持的音频格式或文件输入路径有误")
         
     def __check_flac_metadata(self):
-        try:
-            audio = flac.FLAC(self.audio)
-        except:
-            audio = flac.FLAC()
-            self.__modify_flac_metadata()
+        audio = flac.FLAC(self.audio)
         title_check = audio.get("TITLE")
         artist_check = audio.get("ARTIST")
-        lyrics_check = audio.get("LYRICS")
+        lyrics_check = audio.get("LYRICS") or audio.get("UNSYNCEDLYRICS")
         cover_check = audio.pictures
         return bool(title_check), bool(artist_check), bool(lyrics_check), bool(cover_check)
     
     def __check_mp3_metadata(self):
-        try:
-            audio = id3.ID3(self.audio)
-        except:
-            audio = id3.ID3()
-            self.__modify_mp3_metadata()
+        audio = self.__load_id3()
         title_check = audio.get("TIT2")
         artist_check = audio.get("TPE1")
-        lyrics_check = audio.get("TXXX")
+        lyrics_check = audio.getall("TXXX:Lyrics") or audio.getall("USLT")
         cover_check = audio.getall("APIC")
         return bool(title_check), bool(artist_check), bool(lyrics_check), bool(cover_check)
     
@@ -103,17 +252,361 @@ class AudioProcessing():
         elif self.audio_format == "audio/flac":
             return self.__check_flac_metadata()
         else:
-            raise "不支持的音频格式或文件输入路径有误"
+            raise ValueError("不支持的音频格式或文件输入路径有误")
         
+    @staticmethod
+    def __number(value):
+        # 音轨号/碟号可能是 "3/12" 的形式, 年份可能是完整日期
+        match = re.match(r'\s*(\d+)', value) if value else None
+        return int(match.group(1)) if match else None
+
+    @staticmethod
+    def __mp4_value(value):
+        # trkn/disk 为 (序号, 总数) 元组, ---- 开头的自定义字段为 utf-8 字节
+        if isinstance(value, tuple):
+            value = value[0]
+        return value.decode('utf-8', errors='replace') if isinstance(value, bytes) else str(value)
+
+    def metadata_get(self):
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            metadata = {name: str(audio[key].text[0]) if key in audio and audio[key].text else None for name, key in ID3_KEYS.items()}
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            metadata = {name: audio[key][0] if key in audio else None for name, key in VORBIS_KEYS.items()}
+        elif self.audio_format == "audio/mp4":
+            audio = mp4.MP4(self.audio)
+            tags = audio.tags or {}
+            metadata = {name: self.__mp4_value(tags[key][0]) if tags.get(key) else None for name, key in MP4_KEYS.items()}
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+        for name in ["tracknumber", "discnumber", "year", "bpm"]:
+            metadata[name] = self.__number(metadata[name])
+        for name, plural in [("artist", "artists"), ("genre", "genres")]:
+            # artist/genre 保留标签原文(如 AC/DC), 拆分后的结果只放在 artists/genres 中
+            values = self.__values_get(audio, name)
+            metadata[plural] = self.__split_values(values)
+            metadata[name] = " / ".join(values) or None
+        metadata["rating"] = self.__rating_get(audio)
+        metadata.update(self.__replaygain_get(audio))
+        metadata["chapters"] = self.__chapters_get(audio)
+        return metadata
+
+    def __rating_get(self, audio):
+        # 返回 0~5 星, 没有评分时返回 None
+        if self.audio_format == "audio/mp3":
+            frames = audio.getall("POPM")
+            if not frames:
+                return None
+            value = frames[0].rating
+            return min(range(len(POPM_RATINGS)), key=lambda stars: abs(POPM_RATINGS[stars] - value))
+        elif self.audio_format == "audio/flac":
+            match = re.match(r'\s*(\d+(\.\d+)?)', audio.get("RATING", [""])[0])
+            if not match:
+                return None
+            # foobar2000 写入 1~5, MusicBee 写入 0~100
+            value = float(match.group(1))
+            return round(value) if value <= 5 else round(value / 20)
+        return None
+
+    def rating_set(self, stars):
+        # stars 为 0~5, 0 表示删除评分
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            frames = audio.getall("POPM")
+            count = frames[0].count if frames and hasattr(frames[0], "count") else 0
+            audio.delall("POPM")
+            if stars:
+                audio.add(id3.POPM(email=POPM_EMAIL, rating=POPM_RATINGS[stars], count=count))
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            audio.pop("RATING", None)
+            if stars:
+                audio["RATING"] = str(stars * 20)
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def __values_get(self, audio, name):
+        # 多值字段: id3v2.4 的多个文本, vorbis 的多个同名字段, mp4 的多个值
+        if self.audio_format == "audio/mp3":
+            return [str(text) for text in audio[ID3_KEYS[name]].text] if ID3_KEYS[name] in audio else []
+        elif self.audio_format == "audio/flac":
+            return audio.get(VORBIS_KEYS[name], [])
+        return [str(value) for value in (audio.tags or {}).get(MP4_KEYS[name], [])]
+
+    @staticmethod
+    def __split_values(values):
+        result = []
+        for value in values:
+            for part in VALUE_SEPARATORS.split(value):
+                if part and part not in result:
+                    result.append(part)
+        return result
+
+    def __chapters_get(self, audio):
+        # 返回 [{"title", "start", "end"}], 时间单位为毫秒
+        duration = int(audio.info.length * 1000) if getattr(audio, "info", None) else None
+        if self.audio_format == "audio/mp3":
+            chapters = [{"title": str(frame.sub_frames["TIT2"].text[0]) if "TIT2" in frame.sub_frames else frame.element_id,
+                         "start": frame.start_time, "end": frame.end_time} for frame in audio.getall("CHAP")]
+            return sorted(chapters, key=lambda chapter: chapter["start"])
+        if self.audio_format == "audio/mp4":
+            starts = [(int(chapter.start * 1000), chapter.title) for chapter in audio.chapters or []]
+        else:
+            # flac 使用 CHAPTER001=00:00:00.000 / CHAPTER001NAME=标题 的约定
+            starts = []
+            for key in sorted(key for key in audio.keys() if re.fullmatch(r'(?i)chapter\d+', key)):
+                # 兼容 hh:mm:ss.fff 和 mm:ss.fff, 无法解析的章节直接跳过
+                seconds = 0.0
+                try:
+                    for part in audio[key][0].strip().split(":"):
+                        seconds = seconds * 60 + float(part)
+                except ValueError:
+                    continue
+                name = audio.get(key + "NAME")
+                starts.append((int(round(seconds * 1000)), name[0] if name else key))
+        starts.sort()
+        return [{"title": title, "start": start, "end": starts[index + 1][0] if index + 1 < len(starts) else duration}
+                for index, (start, title) in enumerate(starts)]
+
+    def __replaygain_get(self, audio):
+        # mp3 的 ReplayGain 存在 TXXX 中, 不同软件写入的大小写不一致
+        if self.audio_format == "audio/mp3":
+            values = {frame.desc.lower(): str(frame.text[0]) for frame in audio.getall("TXXX") if frame.text}
+        elif self.audio_format == "audio/mp4":
+            values = {key.split(":")[-1].lower(): bytes(value[0]).decode("utf-8", "replace")
+                      for key, value in (audio.tags or {}).items() if key.startswith("----:")}
+        else:
+            values = {key: audio[key][0] for key in REPLAYGAIN_KEYS if key in audio}
+        replaygain = {}
+        for key in REPLAYGAIN_KEYS:
+            match = re.match(r'\s*([-+]?\d+(\.\d+)?)', values.get(key, ""))
+            value = float(match.group(1)) if match else None
+            # R128 为 Q7.8 定点数
+            replaygain[key] = value / 256 if key.startswith("r128") and value is not None else value
+        return replaygain
+
+    def replaygain_set(self, values):
+        # values 的键为 REPLAYGAIN_KEYS 中的 replaygain_*, 统一以大写写入
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            for frame in audio.getall("TXXX"):
+                if frame.desc.lower() in values:
+                    audio.delall(frame.HashKey)
+            for key, value in values.items():
+                audio.add(id3.TXXX(encoding=3, desc=key.upper(), text=value))
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            for key, value in values.items():
+                audio[key.upper()] = value
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def musicbrainz_set(self, ids):
+        # 与 Picard 的写法一致: 录音 id 在 mp3 中写入 UFID, 其余写入 TXXX
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            if ids.get("musicbrainz_recordingid"):
+                audio.delall("UFID:http://musicbrainz.org")
+                audio.add(id3.UFID(owner="http://musicbrainz.org", data=ids["musicbrainz_recordingid"].encode()))
+            for key, desc in MUSICBRAINZ_KEYS.items():
+                if ids.get(key):
+                    audio.delall(f"TXXX:{desc}")
+                    audio.add(id3.TXXX(encoding=3, desc=desc, text=ids[key]))
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            if ids.get("musicbrainz_recordingid"):
+                audio["MUSICBRAINZ_TRACKID"] = ids["musicbrainz_recordingid"]
+            for key in MUSICBRAINZ_KEYS:
+                if ids.get(key):
+                    audio[key.upper()] = ids[key]
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def gain_get(self, album=False):
+        # 返回以 ReplayGain 2.0 (-18 LUFS) 为参考的增益(dB), 没有增益信息时返回 None
+        metadata = self.metadata_get()
+        for prefix in (["album", "track"] if album else ["track"]):
+            if metadata[f"replaygain_{prefix}_gain"] is not None:
+                return metadata[f"replaygain_{prefix}_gain"]
+            if metadata[f"r128_{prefix}_gain"] is not None:
+                return metadata[f"r128_{prefix}_gain"] + 5
+        return None
+
+    @staticmethod
+    def __mojibake_bytes(value):
+        # 只有全部字符都在 latin-1 范围内且含有高位字节时, 才可能是被当作 latin-1 读取的 GBK/Big5 等编码
+        if not value or any(ord(char) > 0xff for char in value) or all(ord(char) < 0x80 for char in value):
+            return None
+        return value.encode("latin-1")
+
+    @staticmethod
+    def __chaos(data, encoding):
+        match = next(iter(from_bytes(data, cp_isolation=[encoding])), None)
+        return match.chaos if match is not None else float("inf")
+
+    def __mojibake_encoding(self, data):
+        # 每个字段单独判断; 标签文本很短, 得分相同时按 TAG_ENCODINGS 的顺序选择, 只有解码出平假名/片假名时才优先日文
+        matches = list(from_bytes(data, cp_isolation=TAG_ENCODINGS))
+        if not matches:
+            return None
+        least = min(match.chaos for match in matches)
+        tied = [match.encoding for match in matches if match.chaos == least]
+        if "shift_jis" in tied and re.search(r'[\u3040-\u30ff]', data.decode("shift_jis", "ignore")):
+            encoding = "shift_jis"
+        else:
+            encoding = next(encoding for encoding in TAG_ENCODINGS if encoding in tied)
+        try:
+            text = data.decode(encoding)
+        except UnicodeDecodeError:
+            return None
+        # 解码结果至少要有两个中日文字符, 且不能吞掉 ascii 字符:
+        # 西文的 "ó"、"ü" 后面通常紧跟字母, 按 GBK 解码时会和字母拼成一个汉字
+        if len(re.findall(r'[\u3040-\u30ff\u4e00-\u9fff]', text)) < 2:
+            return None
+        if sum(char < "\x80" for char in text) != sum(byte < 0x80 for byte in data):
+            return None
+        # 还要明显比按 cp1252/latin-1 读取更合理: 西文很少连续出现三个以上的非 ascii 字符
+        latin = data.decode("latin-1")
+        if not re.search(r'[\x80-\xff]{3}', latin) and self.__chaos(data, "cp1252") <= least + 0.1:
+            return None
+        return encoding
+
+    def mojibake_get(self, include_flac=False):
+        # 返回 {字段: (原文本, 修复后文本)}
+        # flac 的 vorbis comment 按规范必须是 utf-8, 只有指定 include_flac 时才检查
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            values = {frame.HashKey: str(frame) for frame in audio.values()
+                      if isinstance(frame, id3.TextFrame) and frame.encoding == id3.Encoding.LATIN1}
+        elif self.audio_format == "audio/flac":
+            if not include_flac:
+                return {}
+            audio = flac.FLAC(self.audio)
+            values = {key: value for key, value in audio.items() for value in value[:1]}
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+        repairs = {}
+        for key, value in values.items():
+            data = self.__mojibake_bytes(value)
+            encoding = self.__mojibake_encoding(data) if data is not None else None
+            if encoding is not None:
+                repairs[key] = (value, data.decode(encoding))
+        return repairs
+
+    def mojibake_repair(self, repairs):
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            for key, (old, new) in repairs.items():
+                audio[key].encoding = id3.Encoding.UTF8
+                audio[key].text = new.split("\x00")
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            for key, (old, new) in repairs.items():
+                audio[key] = [new] + audio[key][1:]
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def metadata_set(self, fields):
+        # fields 的键与 metadata_get 返回的一致, 值为 None 或空字符串时删除该字段
+        unknown = set(fields) - set(ID3_KEYS)
+        if unknown:
+            raise ValueError(f"不支持的字段: {', '.join(sorted(unknown))}")
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            for name, value in fields.items():
+                audio.delall(ID3_KEYS[name])
+                if value not in (None, ""):
+                    audio.add(getattr(id3, ID3_KEYS[name])(encoding=3, text=str(value)))
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            for name, value in fields.items():
+                audio.pop(VORBIS_KEYS[name], None)
+                if value not in (None, ""):
+                    audio[VORBIS_KEYS[name]] = str(value)
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def lyrics_get(self):
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            frames = audio.getall("TXXX:Lyrics") + audio.getall("USLT")
+            return str(frames[0]) if frames else None
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            lyrics = audio.get("LYRICS") or audio.get("UNSYNCEDLYRICS")
+            return lyrics[0] if lyrics else None
+        elif self.audio_format == "audio/mp4":
+            lyrics = (mp4.MP4(self.audio).tags or {}).get("\xa9lyr")
+            return str(lyrics[0]) if lyrics else None
+        raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def lyrics_set(self, lyrics):
+        # 覆盖已有歌词, lyrics 为 None 时删除歌词
+        if self.audio_format == "audio/mp3":
+            audio = self.__load_id3()
+            audio.delall("TXXX:Lyrics")
+            audio.delall("USLT")
+            if lyrics is not None:
+                audio.add(id3.TXXX(encoding=3, desc="Lyrics", text=lyrics))
+            return audio.save(self.audio)
+        elif self.audio_format == "audio/flac":
+            audio = flac.FLAC(self.audio)
+            audio.pop("LYRICS", None)
+            audio.pop("UNSYNCEDLYRICS", None)
+            if lyrics is not None:
+                audio["LYRICS"] = lyrics
+            return audio.save()
+        elif self.audio_format == "audio/mp4":
+            audio = mp4.MP4(self.audio)
+            if audio.tags is None:
+                audio.add_tags()
+            audio.tags.pop("\xa9lyr", None)
+            if lyrics is not None:
+                audio.tags["\xa9lyr"] = [lyrics]
+            return audio.save()
+        else:
+            raise ValueError("不支持的音频格式或文件输入路径有误")
+
+    def duration_get(self):
+        audio = File(self.audio)
+        return int(audio.info.length * 1000) if audio is not None else None
+
+    def quality_get(self):
+        # 音频流的属性, 也用于比较同一首歌的不同版本: 无损格式优先, 其次比较码率
+        audio = File(self.audio)
+        if audio is None:
+            return None
+        codec = getattr(audio.info, "codec_description", None) or self.audio_format.split("/")[-1].upper()
+        return {
+            "format": self.audio_format.split("/")[-1],
+            "codec": codec,
+            "bitrate": round(getattr(audio.info, "bitrate", 0) / 1000),
+            "sample_rate": getattr(audio.info, "sample_rate", None),
+            "bit_depth": getattr(audio.info, "bits_per_sample", None) or None,
+            "channels": getattr(audio.info, "channels", None),
+            "lossless": self.audio_format == "audio/flac" or codec == "ALAC",
+        }
+
     def metadata_delete(self):
         if self.audio_format == "audio/mp3":
             audio = id3.ID3(self.audio)
             audio.delete()
             return audio.save()
         elif self.audio_format == "audio/flac":
-            audio = flac.FLAC(self)
+            audio = flac.FLAC(self.audio)
             audio.delete()
             audio.clear_pictures()
             return audio.save()
         else:
-            raise "不支持的音频格式或文件输入路径有误"
+            raise ValueError("不支持的音频格式或文件输入路径有误")
@@ -0,0 +1,142 @@
+from urllib.parse import urlparse
+from urllib.request import url2pathname
+from xml.etree import ElementTree
+from xml.sax.saxutils import escape
+from lyrics_processing import LyricsProcessing
+import configparser
+import os
+import pathlib
+
+PLAYLIST_EXTENSIONS = [".m3u", ".m3u8", ".xspf", ".pls"]
+XSPF_NS = "http://xspf.org/ns/0/"
+
+
+class PlaylistProcessing():
+
+    def __init__(self, playlist) -> None:
+        self.playlist = playlist
+        self.format = os.path.splitext(playlist)[1].lower()
+        if self.format not in PLAYLIST_EXTENSIONS:
+            raise ValueError(f"不支持的播放列表格式: {self.format}")
+
+    @staticmethod
+    def is_playlist(path):
+        return os.path.splitext(path)[1].lower() in PLAYLIST_EXTENSIONS
+
+    def __resolve(self, location):
+        # 播放列表中的相对路径相对于播放列表所在目录, xspf 使用 file:// uri
+        if location.startswith("file:"):
+            location = url2pathname(urlparse(location).path)
+        elif "://" in location:
+            return None
+        return os.path.normpath(os.path.join(os.path.dirname(os.path.abspath(self.playlist)), location))
+
+    def __relative(self, audio):
+        directory = os.path.dirname(os.path.abspath(self.playlist))
+        try:
+            return os.path.relpath(os.path.abspath(audio), directory)
+        except ValueError:
+            # windows 下不同盘符无法使用相对路径
+            return os.path.abspath(audio)
+
+    def __text(self):
+        with open(self.playlist, 'rb') as f:
+            content = f.read()
+        return content.decode('utf-8-sig') if self.format in [".m3u8", ".xspf"] else LyricsProcessing.decode(content)
+
+    def entries(self):
+        # 返回 (原始位置, 本地路径, 时长毫秒) 的列表, 网络流等无法对应到本地文件的条目路径为 None, 没有记录时长时为 None
+        with open(self.playlist, 'rb') as f:
+            content = f.read()
+        entries = []
+        if self.format == ".xspf":
+            root = ElementTree.fromstring(content)
+            for track in root.iter(f"{{{XSPF_NS}}}track"):
+                location = track.find(f"{{{XSPF_NS}}}location")
+                if location is None or not location.text:
+                    continue
+                duration = track.find(f"{{{XSPF_NS}}}duration")
+                entries.append((location.text.strip(), self.__number(duration.text if duration is not None else None)))
+        elif self.format == ".pls":
+            parser = configparser.ConfigParser(interpolation=None, strict=False)
+            parser.read_string(LyricsProcessing.decode(content))
+            section = next((name for name in parser.sections() if name.lower() == "playlist"), None)
+            values = parser[section] if section else {}
+            numbers = sorted(int(key[4:]) for key in values if key.lower().startswith("file") and key[4:].isdigit())
+            for number in numbers:
+                length = self.__number(values.get(f"length{number}"))
+                entries.append((values[f"file{number}"], length * 1000 if length is not None else None))
+        else:
+            text = content.decode('utf-8-sig') if self.format == ".m3u8" else LyricsProcessing.decode(content)
+            length = None
+            for line in text.splitlines():
+                line = line.strip()
+                if line.upper().startswith("#EXTINF:"):
+                    length = self.__number(line[8:].split(",", 1)[0])
+                elif line and not line.startswith('#'):
+                    entries.append((line, length * 1000 if length is not None else None))
+                    length = None
+        return [(location, self.__resolve(location), duration) for location, duration in entries]
+
+    @staticmethod
+    def __number(value):
+        # 时长未知时播放列表中常写作 -1
+        try:
+            number = int(float(value))
+        except (TypeError, ValueError):
+            return None
+        return number if number > 0 else None
+
+    def read(self):
+        return [audio for location, audio, duration in self.entries() if audio]
+
+    def relocate(self, changes):
+        # 只替换 changes 中 {原始位置: 新路径} 对应的条目, 其余内容(标题、注释、网络流)原样保留
+        lines = self.__text().splitlines(keepends=True)
+        for index, line in enumerate(lines):
+            stripped = line.strip()
+            if self.format == ".xspf":
+                for location, audio in changes.items():
+                    line = line.replace(f">{escape(location)}<", f">{escape(pathlib.Path(os.path.abspath(audio)).as_uri())}<")
+            elif self.format == ".pls":
+                key, _, location = stripped.partition("=")
+                if key.lower().startswith("file") and location in changes:
+                    line = line.replace(location, self.__relative(changes[location]), 1)
+            elif stripped in changes and not stripped.startswith('#'):
+                line = line.replace(stripped, self.__relative(changes[stripped]), 1)
+            lines[index] = line
+        with open(self.playlist, 'w', encoding='utf-8') as f:
+            f.write("".join(lines))
+
+    def write(self, tracks):
+        # tracks 为 (路径, 标题, 歌手, 时长毫秒) 的列表, 标题等缺失时为 None
+        if self.format == ".xspf":
+            ElementTree.register_namespace('', XSPF_NS)
+            root = ElementTree.Element(f"{{{XSPF_NS}}}playlist", version="1")
+            track_list = ElementTree.SubElement(root, f"{{{XSPF_NS}}}trackList")
+            for audio, title, artist, duration in tracks:
+                track = ElementTree.SubElement(track_list, f"{{{XSPF_NS}}}track")
+                ElementTree.SubElement(track, f"{{{XSPF_NS}}}location").text = pathlib.Path(os.path.abspath(audio)).as_uri()
+                for name, value in [("title", title), ("creator", artist), ("duration", duration)]:
+                    if value:
+                        ElementTree.SubElement(track, f"{{{XSPF_NS}}}{name}").text = str(value)
+            ElementTree.indent(root)
+            content = ElementTree.tostring(root, encoding="unicode", xml_declaration=True) + "\n"
+        elif self.format == ".pls":
+            lines = ["[playlist]"]
+            for number, (audio, title, artist, duration) in enumerate(tracks, 1):
+                lines.append(f"File{number}={self.__relative(audio)}")
+                if title:
+                    lines.append(f"Title{number}={artist + ' - ' if artist else ''}{title}")
+                lines.append(f"Length{number}={round(duration / 1000) if duration else -1}")
+            lines += [f"NumberOfEntries={len(tracks)}", "Version=2"]
+            content = "\n".join(lines) + "\n"
+        else:
+            lines = ["#EXTM3U"]
+            for audio, title, artist, duration in tracks:
+                if title:
+                    lines.append(f"#EXTINF:{round(duration / 1000) if duration else -1},{artist + ' - ' if artist else ''}{title}")
+                lines.append(self.__relative(audio))
+            content = "\n".join(lines) + "\n"
+        with open(self.playlist, 'w', encoding='utf-8') as f:
+            f.write(content)
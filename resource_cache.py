@@ -0,0 +1,70 @@
+from utils.storage import save_json
+import json
+import os
+import time
+
+
+class ResourceCache():
+
+    def __init__(self, path="resource_cache.json", ttl=7 * 24 * 3600, limit=5000, cover_dir="covers") -> None:
+        self.path = path
+        self.ttl = ttl
+        self.limit = limit
+        self.cover_dir = cover_dir
+        self.cache = self.__load()
+
+    def __load(self):
+        if not os.path.isfile(self.path):
+            return {}
+        try:
+            with open(self.path, 'r', encoding='utf-8') as f:
+                return json.load(f)
+        except ValueError:
+            return {}
+
+    def __save(self):
+        save_json(self.path, self.cache)
+
+    def __evict(self):
+        # 超过条数上限时删除最久未使用的条目, 不再被引用的封面文件一起删除
+        expired = [key for key, entry in self.cache.items() if time.time() - entry["time"] > self.ttl]
+        for key in expired:
+            del self.cache[key]
+        if len(self.cache) > self.limit:
+            for key in sorted(self.cache, key=lambda key: self.cache[key].get("used", self.cache[key]["time"]))[:len(self.cache) - self.limit]:
+                del self.cache[key]
+        referenced = {entry["value"] for key, entry in self.cache.items() if key.startswith("cover|")}
+        for digest in self.__covers():
+            if digest not in referenced:
+                os.remove(os.path.join(self.cover_dir, f"{digest}.jpg"))
+
+    def __covers(self):
+        if not os.path.isdir(self.cover_dir):
+            return []
+        return [os.path.splitext(i)[0] for i in os.listdir(self.cover_dir) if i.endswith(".jpg")]
+
+    def get(self, key):
+        entry = self.cache.get(key)
+        if entry is None or time.time() - entry["time"] > self.ttl:
+            return None
+        # 使用时间只在下次写入时保存
+        entry["used"] = time.time()
+        return entry["value"]
+
+    def set(self, key, value):
+        self.cache[key] = {"time": time.time(), "used": time.time(), "value": value}
+        if len(self.cache) > self.limit:
+            self.__evict()
+        self.__save()
+        return value
+
+    def stats(self):
+        covers = self.__covers()
+        return {
+            "entries": len(self.cache),
+            "expired": sum(time.time() - entry["time"] > self.ttl for entry in self.cache.values()),
+            "limit": self.limit,
+            "size": os.path.getsize(self.path) if os.path.isfile(self.path) else 0,
+            "covers": len(covers),
+            "cover_size": sum(os.path.getsize(os.path.join(self.cover_dir, f"{digest}.jpg")) for digest in covers),
+        }
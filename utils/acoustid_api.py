@@ -0,0 +1,39 @@
+import json
+import subprocess
+import requests
+
+
+class AcoustID:
+    def __init__(self, key, fpcalc='fpcalc'):
+        self._key = key
+        self._fpcalc = fpcalc
+        self._headers = {
+            'User-Agent': 'LyricsAdapter (https://github.com/xwsjjctz/LyricsAdapter)'
+        }
+
+    def fingerprint(self, audio):  # 调用 Chromaprint 的 fpcalc 计算指纹
+        result = subprocess.run([self._fpcalc, '-json', audio], capture_output=True, text=True)
+        if result.returncode != 0:
+            raise RuntimeError(result.stderr.strip() or 'fpcalc failed')
+        data = json.loads(result.stdout)
+        return int(data['duration']), data['fingerprint']
+
+    def lookup(self, duration, fingerprint):
+        resp = requests.post(url='https://api.acoustid.org/v2/lookup',
+                             data={'client': self._key, 'duration': duration, 'fingerprint': fingerprint,
+                                   'meta': 'recordings releasegroups'},
+                             headers=self._headers)
+        resp.raise_for_status()
+        return resp.json().get('results', [])
+
+    def identify(self, audio, min_score=0.5):  # 返回得分最高且带有录音信息的结果
+        for result in sorted(self.lookup(*self.fingerprint(audio)), key=lambda item: -item.get('score', 0)):
+            if result.get('score', 0) < min_score:
+                break
+            for recording in result.get('recordings', []):
+                if recording.get('title') and recording.get('artists'):
+                    return {'title': recording['title'],
+                            'artist': ''.join(artist['name'] + artist.get('joinphrase', '') for artist in recording['artists']),
+                            'score': result['score'],
+                            'musicbrainz_recordingid': recording['id']}
+        return None
@@ -0,0 +1,57 @@
+import requests
+
+
+class Artwork:
+    def __init__(self):
+        self._headers = {
+            'User-Agent': 'LyricsAdapter (https://github.com/xwsjjctz/LyricsAdapter)',
+            'Accept': 'application/json'
+        }
+
+    def search_itunes(self, album, artist, size=1200, limit=5):  # iTunes 的封面地址可以直接替换尺寸
+        resp = requests.get(url='https://itunes.apple.com/search',
+                            params={'term': '{} {}'.format(artist, album), 'entity': 'album', 'limit': limit},
+                            headers=self._headers).json()
+        return [{'source': 'itunes',
+                 'album': item.get('collectionName'),
+                 'artist': item.get('artistName'),
+                 'url': item['artworkUrl100'].replace('100x100bb', '{0}x{0}bb'.format(size)),
+                 'width': size,
+                 'height': size} for item in resp['results'] if item.get('artworkUrl100')]
+
+    def search_cover_art_archive(self, album, artist, limit=5):  # 先在 MusicBrainz 搜索专辑, 再查询 Cover Art Archive
+        resp = requests.get(url='https://musicbrainz.org/ws/2/release/',
+                            params={'query': 'release:"{}" AND artist:"{}"'.format(album, artist),
+                                    'fmt': 'json', 'limit': limit},
+                            headers=self._headers).json()
+        candidates = []
+        for release in resp.get('releases', []):
+            cover = requests.get(url='https://coverartarchive.org/release/{}'.format(release['id']), headers=self._headers)
+            if cover.status_code != 200:
+                continue
+            for image in cover.json().get('images', []):
+                if not image.get('front'):
+                    continue
+                for size in ['1200', '500']:  # 原图没有给出尺寸, 只使用已知尺寸的缩略图
+                    if image.get('thumbnails', {}).get(size):
+                        candidates.append({'source': 'caa',
+                                           'album': release.get('title'),
+                                           'artist': ''.join(credit.get('name', '') + credit.get('joinphrase', '')
+                                                             for credit in release.get('artist-credit', [])),
+                                           'url': image['thumbnails'][size],
+                                           'width': int(size),
+                                           'height': int(size)})
+                        break
+        return candidates
+
+    def search(self, source, album, artist):
+        if source == 'itunes':
+            return self.search_itunes(album, artist)
+        elif source == 'caa':
+            return self.search_cover_art_archive(album, artist)
+        return []
+
+    def download(self, url):
+        resp = requests.get(url, headers=self._headers)
+        resp.raise_for_status()
+        return resp.content
@@ -0,0 +1,39 @@
+import hashlib
+import requests
+
+
+class LRCLIB:
+    def __init__(self):
+        self._base_url = 'https://lrclib.net/api'
+        self._headers = {
+            'User-Agent': 'LyricsAdapter (https://github.com/xwsjjctz/LyricsAdapter)',
+            'Content-Type': 'application/json'
+        }
+
+    def request_challenge(self):  # 获取发布歌词前需要完成的工作量证明
+        resp = requests.post(url='{}/request-challenge'.format(self._base_url), headers=self._headers)
+        resp.raise_for_status()
+        return resp.json()
+
+    def solve_challenge(self, prefix, target):  # 找到 sha256(prefix + nonce) 不大于 target 的 nonce
+        target = bytes.fromhex(target)
+        nonce = 0
+        while hashlib.sha256('{}{}'.format(prefix, nonce).encode()).digest() > target:
+            nonce += 1
+        return str(nonce)
+
+    def publish(self, track_name, artist_name, album_name, duration, plain_lyrics, synced_lyrics):
+        challenge = self.request_challenge()
+        nonce = self.solve_challenge(challenge['prefix'], challenge['target'])
+        data = {
+            'trackName': track_name,
+            'artistName': artist_name,
+            'albumName': album_name,
+            'duration': duration,
+            'plainLyrics': plain_lyrics,
+            'syncedLyrics': synced_lyrics
+        }
+        headers = dict(self._headers, **{'X-Publish-Token': '{}:{}'.format(challenge['prefix'], nonce)})
+        resp = requests.post(url='{}/publish'.format(self._base_url), headers=headers, json=data)
+        resp.raise_for_status()
+        return resp.status_code
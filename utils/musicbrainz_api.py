@@ -0,0 +1,42 @@
+import requests
+
+
+class MusicBrainz:
+    def __init__(self):
+        self._base_url = 'https://musicbrainz.org/ws/2'
+        self._headers = {
+            'User-Agent': 'LyricsAdapter (https://github.com/xwsjjctz/LyricsAdapter)',
+            'Accept': 'application/json'
+        }
+
+    @staticmethod
+    def artist_credit(credits):  # 把 artist-credit 数组拼成显示用的歌手名
+        return ''.join(credit.get('name', '') + credit.get('joinphrase', '') for credit in credits or [])
+
+    def search_recording(self, title, artist, duration=None, limit=10):  # duration 为毫秒, 用于给结果排序
+        query = 'recording:"{}"'.format(title) + (' AND artist:"{}"'.format(artist) if artist else '')
+        resp = requests.get(url='{}/recording/'.format(self._base_url),
+                            params={'query': query, 'fmt': 'json', 'limit': limit},
+                            headers=self._headers)
+        resp.raise_for_status()
+        candidates = []
+        for recording in resp.json().get('recordings', []):
+            for release in recording.get('releases', []):
+                media = release.get('media') or [{}]
+                track = (media[0].get('track') or [{}])[0]
+                candidates.append({
+                    'title': recording.get('title'),
+                    'artist': self.artist_credit(recording.get('artist-credit')),
+                    'album': release.get('title'),
+                    'albumartist': self.artist_credit(release.get('artist-credit')) or None,
+                    'tracknumber': track.get('number'),
+                    'discnumber': media[0].get('position'),
+                    'year': (release.get('date') or '')[:4] or None,
+                    'length': recording.get('length'),
+                    'musicbrainz_recordingid': recording['id'],
+                    'musicbrainz_albumid': release['id'],
+                    'musicbrainz_artistid': (recording.get('artist-credit') or [{}])[0].get('artist', {}).get('id'),
+                })
+        if duration is not None:
+            candidates.sort(key=lambda candidate: abs((candidate['length'] or 0) - duration))
+        return candidates
@@ -0,0 +1,55 @@
+import glob
+import hashlib
+import json
+import os
+import shutil
+import tempfile
+import time
+
+
+def save_json(path, data, backups=0, **kwargs):  # 先写入同目录的临时文件再替换, 写入中途崩溃不会损坏原文件
+    if backups and os.path.isfile(path):
+        backup_rotate(path, backups)
+    directory = os.path.dirname(os.path.abspath(path))
+    fd, temp_path = tempfile.mkstemp(prefix='.' + os.path.basename(path) + '.', suffix='.tmp', dir=directory)
+    try:
+        with os.fdopen(fd, 'w', encoding='utf-8') as f:
+            json.dump(data, f, ensure_ascii=False, **kwargs)
+            f.flush()
+            os.fsync(f.fileno())
+        os.replace(temp_path, path)
+    except BaseException:
+        os.remove(temp_path)
+        raise
+
+
+def backup_list(path):
+    # 按时间从新到旧返回 path 的备份, 文件名形如 lyrics_history.json.20240101-120000.bak
+    return sorted(glob.glob(glob.escape(path) + ".*.bak"), reverse=True)
+
+
+def backup_rotate(path, keep):
+    # 把当前文件复制为带时间戳的备份, 只保留最新的 keep 份
+    shutil.copy2(path, f"{path}.{time.strftime('%Y%m%d-%H%M%S')}.bak")
+    for backup in backup_list(path)[keep:]:
+        os.remove(backup)
+
+
+def save_bytes(path, data):
+    directory = os.path.dirname(os.path.abspath(path))
+    fd, temp_path = tempfile.mkstemp(prefix='.' + os.path.basename(path) + '.', suffix='.tmp', dir=directory)
+    try:
+        with os.fdopen(fd, 'wb') as f:
+            f.write(data)
+        os.replace(temp_path, path)
+    except BaseException:
+        os.remove(temp_path)
+        raise
+
+
+def file_hash(path, chunk_size=1 << 20):
+    sha1 = hashlib.sha1()
+    with open(path, 'rb') as f:
+        for chunk in iter(lambda: f.read(chunk_size), b''):
+            sha1.update(chunk)
+    return sha1.hexdigest()